@@ -0,0 +1,127 @@
+//! Random draws from generated lists, compared with counting the available items and skipping
+//! to a random offset, which is how `get_random` worked before. Draws from mostly done lists,
+//! which fall back to counting, and draws with balancing and cooldown rules are measured too.
+//!
+//! Run with `cargo bench --bench draw`.
+
+use std::{
+    fs,
+    hint::black_box,
+    path::{Path, PathBuf},
+};
+
+use chrono::NaiveDate;
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use rednext::{
+    db::{Cooldown, DB, DbFieldDesc, DbFieldType, DbSchema, DrawOptions},
+    rand::{RngExt, SeedableRng, rngs::ChaCha8Rng},
+    sqlite::SqliteDB,
+};
+use rusqlite::{Connection, params};
+
+const SIZES: &[u32] = &[10_000, 100_000, 1_000_000];
+/// Number of distinct genres in the generated lists
+const GENRES: u32 = 10;
+
+/// Creates a list with `size` items with one of the genres each. Only every `open_every` item is
+/// not done.
+fn generate(dir: &Path, name: &str, size: u32, open_every: u32) -> PathBuf {
+    let schema = DbSchema::new(vec![
+        DbFieldDesc::new("title", DbFieldType::Text),
+        DbFieldDesc::new("genre", DbFieldType::Text),
+    ]);
+    SqliteDB::new(dir).create(name, schema).unwrap();
+
+    let path = dir.join(format!("{name}.db"));
+    let mut conn = Connection::open(&path).unwrap();
+    let tx = conn.transaction().unwrap();
+    tx.execute(
+        "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < ?1)
+         INSERT INTO items (title, genre, _rn_position, _rn_created_at, done_at)
+         SELECT 'task ' || i, 'genre ' || (i % ?2), i, '2026-01-01 00:00:00',
+                CASE WHEN i % ?3 <> 0 THEN '2026-02-01 00:00:00' END
+         FROM n",
+        params![size, GENRES, open_every],
+    )
+    .unwrap();
+    tx.commit().unwrap();
+    path
+}
+
+/// The previous implementation: count the available items, then take the one at random offset.
+fn count_and_offset(conn: &Connection, rng: &mut ChaCha8Rng) -> u32 {
+    const FILTER: &str = "done_at IS NULL AND NOT EXISTS (
+        SELECT 1 FROM deps JOIN items AS p ON p.id = deps.depends_on
+        WHERE deps.item_id = items.id AND p.done_at IS NULL
+      )";
+    let count: u32 = conn
+        .query_one(
+            format!("SELECT count(*) FROM items WHERE {FILTER}").as_str(),
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    conn.query_one(
+        format!("SELECT id FROM items WHERE {FILTER} ORDER BY id LIMIT 1 OFFSET ?1").as_str(),
+        params![rng.random_range(0..count)],
+        |row| row.get(0),
+    )
+    .unwrap()
+}
+
+fn draw(c: &mut Criterion) {
+    let dir = std::env::temp_dir().join(format!("rednext-bench-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let db = SqliteDB::new(&dir);
+
+    let mut group = c.benchmark_group("draw");
+    for &size in SIZES {
+        let name = format!("items{size}");
+        let path = generate(&dir, &name, size, 2);
+        let file = db.open(&name).unwrap();
+        let options = DrawOptions::default();
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        group.bench_with_input(BenchmarkId::new("get_random", size), &size, |b, _| {
+            b.iter(|| black_box(file.get_random(&mut rng, &options).unwrap()))
+        });
+
+        let conn = Connection::open(&path).unwrap();
+        group.bench_with_input(BenchmarkId::new("count_and_offset", size), &size, |b, _| {
+            b.iter(|| black_box(count_and_offset(&conn, &mut rng)))
+        });
+
+        let balanced = DrawOptions::default().with_balance_by(Some("genre".to_string()));
+        group.bench_with_input(BenchmarkId::new("balance_by", size), &size, |b, _| {
+            b.iter(|| black_box(file.get_random(&mut rng, &balanced).unwrap()))
+        });
+
+        let time = NaiveDate::from_ymd_opt(2026, 3, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        for id in 1..=3 {
+            file.record_draw(id, time).unwrap();
+        }
+        let cooldown = DrawOptions::default().with_cooldown(vec![Cooldown {
+            field: "genre".to_string(),
+            draws: 3,
+        }]);
+        group.bench_with_input(BenchmarkId::new("cooldown", size), &size, |b, _| {
+            b.iter(|| black_box(file.get_random(&mut rng, &cooldown).unwrap()))
+        });
+
+        // One item of a thousand is left, so probing random ids misses and the items are counted
+        let name = format!("mostly_done{size}");
+        generate(&dir, &name, size, 1000);
+        let file = db.open(&name).unwrap();
+        group.bench_with_input(BenchmarkId::new("mostly_done", size), &size, |b, _| {
+            b.iter(|| black_box(file.get_random(&mut rng, &options).unwrap()))
+        });
+    }
+    group.finish();
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+criterion_group!(benches, draw);
+criterion_main!(benches);
@@ -0,0 +1,196 @@
+use chrono::{Days, NaiveDate};
+
+use crate::stats::Period;
+
+/// Braille dot bits, indexed by column and row from the top of the character
+const BRAILLE_DOTS: [[u8; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+const BRAILLE_BASE: u32 = 0x2800;
+const BLOCKS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const DATE_WIDTH: usize = 10;
+
+/// Draws cumulative number of completions from the first completion till today, or till the
+/// last completion if it's later.
+///
+/// `width` is the full width of the chart, including the axis labels.
+pub fn cumulative(
+    completions: &[NaiveDate],
+    total: usize,
+    today: NaiveDate,
+    width: usize,
+    height: usize,
+) -> Vec<String> {
+    let Some((first, end)) = date_range(completions, today) else {
+        return vec![];
+    };
+    let label_width = total.to_string().len();
+    let plot_width = width.saturating_sub(label_width + 2).max(1);
+    let days = Period::Day.count(first, end);
+
+    let mut dots = vec![0u8; plot_width * height];
+    let dots_x = plot_width * 2;
+    let dots_y = height * 4;
+    let mut prev_y = None;
+    for x in 0..dots_x {
+        let day = first + Days::new((x * days / dots_x) as u64);
+        let done = completions.iter().filter(|d| **d <= day).count();
+        let y = (done * (dots_y - 1) + total / 2) / total.max(1);
+        // Connect the points to get a continuous line
+        let (from, to) = match prev_y {
+            Some(p) if p < y => (p + 1, y),
+            _ => (y, y),
+        };
+        for y in from..=to {
+            let row = dots_y - 1 - y;
+            dots[(row / 4) * plot_width + x / 2] |= BRAILLE_DOTS[x % 2][row % 4];
+        }
+        prev_y = Some(y);
+    }
+
+    let mut lines = dots
+        .chunks(plot_width)
+        .enumerate()
+        .map(|(i, row)| {
+            let plot = row
+                .iter()
+                .map(|b| char::from_u32(BRAILLE_BASE + *b as u32).unwrap_or(' '))
+                .collect::<String>();
+            axis_line(i, height, total, label_width, &plot)
+        })
+        .collect::<Vec<_>>();
+    lines.extend(x_axis(label_width, plot_width, first, end));
+    lines
+}
+
+/// Draws number of completions per week, from the first completion till today. Only the last weeks
+/// are shown, if the width is not enough.
+pub fn weekly(
+    completions: &[NaiveDate],
+    today: NaiveDate,
+    width: usize,
+    height: usize,
+) -> Vec<String> {
+    let Some((first, end)) = date_range(completions, today) else {
+        return vec![];
+    };
+    let mut weeks = vec![0usize; Period::Week.count(first, end)];
+    for d in completions.iter().filter(|d| **d <= end) {
+        weeks[Period::Week.count(first, *d) - 1] += 1;
+    }
+    let max = weeks.iter().copied().max().unwrap_or(0);
+    let label_width = max.to_string().len();
+    let plot_width = width.saturating_sub(label_width + 2).max(2);
+    // Each bar is followed by a gap
+    let shown = weeks.len().min(plot_width / 2);
+    let weeks = &weeks[weeks.len() - shown..];
+    let first_shown = Period::Week.start(end) - Days::new(7 * (shown as u64 - 1));
+
+    let units = weeks
+        .iter()
+        .map(|w| (w * height * 8 + max / 2) / max.max(1))
+        .collect::<Vec<_>>();
+    let mut lines = (0..height)
+        .map(|i| {
+            let base = (height - 1 - i) * 8;
+            let plot = units
+                .iter()
+                .flat_map(|u| [BLOCKS[u.saturating_sub(base).min(8)], ' '])
+                .collect::<String>();
+            axis_line(i, height, max, label_width, &plot)
+        })
+        .collect::<Vec<_>>();
+    lines.extend(x_axis(
+        label_width,
+        shown * 2,
+        first_shown,
+        Period::Week.start(end),
+    ));
+    lines
+}
+
+/// First and last dates of the chart. The chart always ends today: completion times can't be set
+/// in the future, so later ones can only come from a wrong clock and are left out.
+fn date_range(completions: &[NaiveDate], today: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+    let first = completions.iter().filter(|d| **d <= today).min().copied()?;
+    Some((first, today))
+}
+
+/// Adds Y axis with the max value at the top and zero at the bottom.
+fn axis_line(row: usize, height: usize, max: usize, label_width: usize, plot: &str) -> String {
+    let label = if row == 0 {
+        max.to_string()
+    } else if row == height - 1 {
+        "0".to_string()
+    } else {
+        String::new()
+    };
+    let tick = if label.is_empty() { '│' } else { '┤' };
+    format!("{label:>label_width$} {tick}{plot}")
+}
+
+/// Draws X axis with the first and the last dates.
+fn x_axis(label_width: usize, plot_width: usize, from: NaiveDate, to: NaiveDate) -> Vec<String> {
+    let axis = format!("{:label_width$} └{}", "", "─".repeat(plot_width));
+    let dates = if from == to || plot_width < DATE_WIDTH * 2 + 1 {
+        format!("{:label_width$}  {from}", "")
+    } else {
+        let gap = plot_width - DATE_WIDTH * 2;
+        format!("{:label_width$}  {from}{:gap$}{to}", "", "")
+    };
+    vec![axis, dates]
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::chart::{cumulative, weekly};
+
+    fn date(m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_cumulative() {
+        let completions = vec![date(3, 1), date(3, 2), date(3, 3), date(3, 4)];
+        let lines = cumulative(&completions, 4, date(3, 4), 28, 2);
+        assert_eq!(
+            lines,
+            vec![
+                "4 ┤⠀⠀⠀⠀⠀⠀⢀⣀⣀⣀⣀⣀⡠⠤⠤⠤⠤⠤⠤⠋⠉⠉⠉⠉⠉",
+                "0 ┤⠒⠒⠒⠒⠒⠒⠊⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀",
+                "  └─────────────────────────",
+                "   2026-03-01     2026-03-04",
+            ]
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_weekly() {
+        // 2026-03-02 is Monday
+        let completions = vec![date(3, 2), date(3, 3), date(3, 4), date(3, 4), date(3, 17)];
+        let lines = weekly(&completions, date(3, 18), 10, 2);
+        assert_eq!(
+            lines,
+            vec!["4 ┤█     ", "0 ┤█   ▄ ", "  └──────", "   2026-03-02"]
+                .into_iter()
+                .map(String::from)
+                .collect::<Vec<_>>()
+        );
+        assert!(weekly(&[], date(3, 18), 10, 2).is_empty());
+    }
+
+    #[test]
+    fn test_completion_after_today() {
+        let completions = vec![date(3, 2), date(3, 16)];
+        let lines = weekly(&completions, date(3, 4), 10, 2);
+        assert_eq!(lines[0], "1 ┤█ ");
+        assert_eq!(lines[3], "   2026-03-02");
+
+        let lines = cumulative(&completions, 2, date(3, 4), 28, 2);
+        assert_eq!(lines[3], "   2026-03-02     2026-03-04");
+        assert!(cumulative(&completions[1..], 1, date(3, 4), 28, 2).is_empty());
+    }
+}
@@ -0,0 +1,268 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow};
+use chrono::{NaiveDateTime, format::StrftimeItems};
+use comfy_table::{
+    Table,
+    presets::{ASCII_FULL, ASCII_MARKDOWN, NOTHING, UTF8_FULL},
+};
+use dirs::{config_dir, home_dir};
+use rednext::error::RednextError;
+use serde::Deserialize;
+
+use crate::{OutputFormat, Strategy};
+
+pub const CONFIG_FILE: &str = "config.toml";
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// Settings from the config file
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// File used by commands working with several files, when no file is given
+    pub default_file: Option<String>,
+    /// Output format of the files list
+    pub format: OutputFormat,
+    pub table_style: TableStyle,
+    /// Format of the timestamps in the output, in `strftime` syntax
+    pub date_format: String,
+    /// Default answer when asked to mark the drawn item as done
+    pub confirm_done: bool,
+    /// Milliseconds to wait while another process is writing to a file
+    pub busy_timeout: Option<u64>,
+    /// Named sets of lists, e.g. work and personal ones
+    pub profiles: BTreeMap<String, Profile>,
+    /// Per-file settings
+    pub files: BTreeMap<String, FileConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            default_file: None,
+            format: OutputFormat::Table,
+            table_style: TableStyle::Default,
+            date_format: DEFAULT_DATE_FORMAT.to_string(),
+            confirm_done: true,
+            busy_timeout: None,
+            profiles: BTreeMap::new(),
+            files: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TableStyle {
+    #[default]
+    Default,
+    Ascii,
+    Markdown,
+    Full,
+    Plain,
+}
+
+impl TableStyle {
+    pub fn preset(self) -> &'static str {
+        match self {
+            TableStyle::Default => "││──╞═╪╡│    ┬┴┌┐└┘",
+            TableStyle::Ascii => ASCII_FULL,
+            TableStyle::Markdown => ASCII_MARKDOWN,
+            TableStyle::Full => UTF8_FULL,
+            TableStyle::Plain => NOTHING,
+        }
+    }
+}
+
+/// Settings overriding command line defaults for a file
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileConfig {
+    pub strategy: Option<Strategy>,
+    pub order_by: Option<String>,
+    pub balance_by: Option<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// Directory with the profile files
+    pub dir: PathBuf,
+}
+
+impl Config {
+    /// Reads the config file. Missing file is the same as the empty one.
+    pub fn load(path: &Path) -> Result<Config> {
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+        let text = fs::read_to_string(path).with_context(|| format!("Cannot read {path:?}"))?;
+        text.parse()
+    }
+
+    /// Returns settings of the file, which are empty if not configured.
+    pub fn file(&self, name: &str) -> &FileConfig {
+        static EMPTY: FileConfig = FileConfig {
+            strategy: None,
+            order_by: None,
+            balance_by: None,
+        };
+        self.files.get(name).unwrap_or(&EMPTY)
+    }
+
+    /// Creates table with the configured style.
+    pub fn table(&self) -> Table {
+        let mut table = Table::new();
+        table.load_preset(self.table_style.preset());
+        table
+    }
+
+    pub fn format_time(&self, time: NaiveDateTime) -> String {
+        time.format(&self.date_format).to_string()
+    }
+
+    /// Returns directory with the files. Explicit directory has priority over the profile, and
+    /// the default directory is used if neither is set.
+    pub fn db_dir(&self, dir: Option<&Path>, profile: Option<&str>) -> Result<PathBuf> {
+        if let Some(dir) = dir {
+            return Ok(expand_home(dir));
+        }
+        if let Some(name) = profile {
+            let profile = self.profiles.get(name).ok_or_else(|| {
+                let known = self.profiles.keys().cloned().collect::<Vec<_>>();
+                RednextError::InvalidInput(if known.is_empty() {
+                    format!("Unknown profile {name}, no profiles are defined in {CONFIG_FILE}")
+                } else {
+                    format!(
+                        "Unknown profile {name}, known profiles: {}",
+                        known.join(", ")
+                    )
+                })
+            })?;
+            return Ok(expand_home(&profile.dir));
+        }
+        default_dir()
+    }
+}
+
+impl std::str::FromStr for Config {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let config: Config = toml::from_str(s).context(RednextError::InvalidInput(
+            "Invalid config file".to_string(),
+        ))?;
+        // Invalid format would panic on output
+        StrftimeItems::new(&config.date_format)
+            .parse()
+            .map_err(|_| {
+                RednextError::InvalidInput(format!("Invalid date format '{}'", config.date_format))
+            })?;
+        Ok(config)
+    }
+}
+
+/// Default directory with the files, which also holds the config file.
+pub fn default_dir() -> Result<PathBuf> {
+    config_dir().map(|d| d.join("rednext")).ok_or_else(|| {
+        anyhow!("Cannot find configuration directory, use --db-dir or REDNEXT_DIR to set the path")
+    })
+}
+
+/// Replaces leading `~` with the home directory.
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use chrono::NaiveDate;
+
+    use crate::{
+        OutputFormat, Strategy,
+        config::{Config, FileConfig, TableStyle},
+    };
+
+    #[test]
+    fn test_profiles() {
+        let config: Config = r#"
+            [profiles.work]
+            dir = "/srv/work"
+
+            [profiles.home]
+            dir = "/home/user/lists"
+        "#
+        .parse()
+        .unwrap();
+        assert_eq!(
+            config.db_dir(None, Some("work")).unwrap(),
+            PathBuf::from("/srv/work")
+        );
+        assert_eq!(
+            config
+                .db_dir(Some(Path::new("/tmp/lists")), Some("work"))
+                .unwrap(),
+            PathBuf::from("/tmp/lists")
+        );
+        let err = config.db_dir(None, Some("play")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown profile play, known profiles: home, work"
+        );
+
+        assert_eq!("".parse::<Config>().unwrap(), Config::default());
+        assert!("date_format = \"%Q\"".parse::<Config>().is_err());
+        assert!(
+            "[profiles.work]\npath = \"/srv\""
+                .parse::<Config>()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_settings() {
+        let config: Config = r#"
+            default_file = "books"
+            format = "json"
+            table_style = "markdown"
+            date_format = "%d.%m.%Y"
+            confirm_done = false
+            busy_timeout = 10000
+
+            [files.books]
+            strategy = "sequential"
+            order_by = "series"
+        "#
+        .parse()
+        .unwrap();
+        assert_eq!(config.default_file.as_deref(), Some("books"));
+        assert_eq!(config.format, OutputFormat::Json);
+        assert_eq!(config.table_style, TableStyle::Markdown);
+        assert!(!config.confirm_done);
+        assert_eq!(config.busy_timeout, Some(10000));
+        let time = NaiveDate::from_ymd_opt(2026, 3, 1)
+            .unwrap()
+            .and_hms_opt(10, 0, 0)
+            .unwrap();
+        assert_eq!(config.format_time(time), "01.03.2026");
+        assert_eq!(
+            config.file("books"),
+            &FileConfig {
+                strategy: Some(Strategy::Sequential),
+                order_by: Some("series".to_string()),
+                balance_by: None,
+            }
+        );
+        assert_eq!(config.file("movies"), &FileConfig::default());
+        assert!("table_style = \"fancy\"".parse::<Config>().is_err());
+    }
+}
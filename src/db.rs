@@ -1,38 +1,476 @@
-use std::fmt::{Display, Formatter};
+use std::{
+    fmt::{Display, Formatter},
+    ops::Range,
+    str::FromStr,
+};
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use chrono::NaiveDateTime;
+use rand::Rng;
 use strum::{Display, EnumString};
 
-pub trait DB {
+use crate::error::RednextError;
+
+/// Storage of the files. The trait is sealed, so methods can be added without breaking changes.
+pub trait DB: sealed::Sealed {
     fn list_files(&self) -> Result<Vec<String>>;
     fn open(&self, name: &str) -> Result<Box<dyn DBFile>>;
     fn delete(&self, name: &str) -> Result<()>;
     fn create(&self, name: &str, schema: DbSchema) -> Result<Box<dyn DBFile>>;
+    /// Reads schema and summary of the file without upgrading or otherwise changing it.
+    fn summary(&self, name: &str) -> Result<(DbSchema, FileSummary)>;
 }
 
-pub trait DBFile {
+/// List of items. The trait is sealed, same as `DB`.
+pub trait DBFile: sealed::Sealed {
     fn schema(&self) -> DbSchema;
-    fn list_items(&self) -> Result<Vec<DbItem>>;
-    fn list_done(&self) -> Result<Vec<DbItem>>;
-    fn list_undone(&self) -> Result<Vec<DbItem>>;
-    fn insert(&self, fields: &[DbField]) -> Result<()>;
+    /// Calls `f` for each item matching the query, reading the items one by one. Iteration stops
+    /// at the first error returned by `f`.
+    fn for_each_item(
+        &self,
+        query: &ItemQuery,
+        f: &mut dyn FnMut(DbItem) -> Result<()>,
+    ) -> Result<()>;
+    /// Runs `f` in a read transaction, so all the queries inside see the same items, even if
+    /// other processes change the file meanwhile.
+    fn read_snapshot(&self, f: &mut dyn FnMut() -> Result<()>) -> Result<()>;
+
+    fn query(&self, query: &ItemQuery) -> Result<Vec<DbItem>> {
+        let mut items = Vec::new();
+        self.for_each_item(query, &mut |item| {
+            items.push(item);
+            Ok(())
+        })?;
+        Ok(items)
+    }
+
+    /// Number of items matching the query, ignoring limit and offset
+    fn count(&self, query: &ItemQuery) -> Result<u32>;
+
+    fn list_items(&self) -> Result<Vec<DbItem>> {
+        self.query(&ItemQuery::default())
+    }
+
+    fn list_undone(&self) -> Result<Vec<DbItem>> {
+        self.query(&ItemQuery::status(ItemStatus::Undone))
+    }
+
+    fn insert(&self, fields: &[DbField], time: NaiveDateTime) -> Result<()>;
     fn delete(&self, id: u32) -> Result<()>;
     fn get(&self, id: u32) -> Result<Option<DbItem>>;
-    fn get_random(&self) -> Result<Option<DbItem>>;
+    fn get_random(&self, rng: &mut dyn Rng, options: &DrawOptions) -> Result<Option<DbItem>>;
+    /// First available item in the manual order, or ordered by the field. With `max_effort`,
+    /// only items fitting into this number of minutes are taken.
+    fn get_next(&self, order_by: Option<&str>, max_effort: Option<u32>) -> Result<Option<DbItem>>;
+    /// Sets completion time of the item, whether it's done or not.
     fn done(&self, id: u32, time: NaiveDateTime) -> Result<()>;
+    /// Marks the item as done, if it's not done yet. Fails with `RednextError::Conflict` if it
+    /// is, e.g. when another process completed the same item after it was drawn.
+    fn complete(&self, id: u32, time: NaiveDateTime) -> Result<()>;
     fn undone(&self, id: u32) -> Result<()>;
-    fn find(&self, item_name: &str) -> Result<Vec<DbItem>>;
+    /// Full-text search over the text fields, best matches first. Words ending with `*` are
+    /// prefixes, and quoted text is a phrase. If `fields` is not empty, only these fields are
+    /// searched, and each of them must be a text field. Found items must also pass all the
+    /// filters. Empty query matches all items passing the filters.
+    fn find(&self, query: &str, fields: &[String], filters: &[Filter]) -> Result<Vec<FoundItem>> {
+        let mut items = Vec::new();
+        self.for_each_found(query, fields, filters, &mut |found| {
+            items.push(found);
+            Ok(())
+        })?;
+        Ok(items)
+    }
+
+    /// Same as `find`, but calls `f` for each found item instead of collecting them.
+    fn for_each_found(
+        &self,
+        query: &str,
+        fields: &[String],
+        filters: &[Filter],
+        f: &mut dyn FnMut(FoundItem) -> Result<()>,
+    ) -> Result<()>;
+    fn add_dependency(&self, id: u32, depends_on: u32) -> Result<()>;
+    fn remove_dependency(&self, id: u32, depends_on: u32) -> Result<()>;
+    fn pin(&self, id: u32) -> Result<()>;
+    fn unpin(&self, id: u32) -> Result<()>;
+    fn move_to(&self, id: u32, position: u32) -> Result<()>;
+    fn set_effort_field(&mut self, field: Option<&str>) -> Result<()>;
+    fn record_draw(&self, id: u32, time: NaiveDateTime) -> Result<()>;
+    /// Times when the item was drawn, oldest first
+    fn draws(&self, id: u32) -> Result<Vec<NaiveDateTime>>;
+    /// IDs of the item prerequisites
+    fn dependencies(&self, id: u32) -> Result<Vec<u32>>;
+    fn summary(&self) -> Result<FileSummary>;
 }
 
-#[derive(Clone)]
+/// Error returned when the file doesn't exist
+#[derive(Debug, PartialEq, Eq)]
+pub struct FileNotFound {
+    pub name: String,
+    /// Existing files with similar names
+    pub suggestions: Vec<String>,
+}
+
+impl std::error::Error for FileNotFound {}
+
+impl Display for FileNotFound {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "File {} is not found", self.name)?;
+        if !self.suggestions.is_empty() {
+            write!(f, ". Did you mean {}?", self.suggestions.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl FileNotFound {
+    /// Creates the error with suggestions from the existing file names.
+    pub fn new(name: &str, files: &[String]) -> Self {
+        FileNotFound {
+            name: name.to_string(),
+            suggestions: similar_names(name, files),
+        }
+    }
+}
+
+/// Returns up to three names close to the given one, the closest first. Names are compared
+/// ignoring case, and allowed distance depends on the name length.
+pub fn similar_names(name: &str, names: &[String]) -> Vec<String> {
+    const MAX_SUGGESTIONS: usize = 3;
+    let name = name.to_lowercase();
+    let max_distance = (name.chars().count() / 3).max(1);
+    let mut similar = names
+        .iter()
+        .map(|n| (edit_distance(&name, &n.to_lowercase()), n))
+        .filter(|(d, _)| *d <= max_distance)
+        .collect::<Vec<_>>();
+    similar.sort();
+    similar
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, n)| n.clone())
+        .collect()
+}
+
+/// Levenshtein distance between the strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut row = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            row[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(row[j] + 1);
+        }
+        prev = row;
+    }
+    prev[b.len()]
+}
+
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct FileSummary {
+    pub items: u32,
+    pub done: u32,
+    /// Time of the last change or draw
+    pub last_activity: Option<NaiveDateTime>,
+}
+
+impl FileSummary {
+    pub fn new(items: u32, done: u32, last_activity: Option<NaiveDateTime>) -> Self {
+        FileSummary {
+            items,
+            done,
+            last_activity,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ItemStatus {
+    #[default]
+    All,
+    Done,
+    Undone,
+    Pinned,
+}
+
+/// Items selection for listings
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct ItemQuery {
+    pub status: ItemStatus,
+    pub filters: Vec<Filter>,
+    /// Sort keys. If empty, the default order for the status is used.
+    pub sort: Vec<SortKey>,
+    pub limit: Option<u32>,
+    pub offset: u32,
+}
+
+impl ItemQuery {
+    pub fn status(status: ItemStatus) -> Self {
+        ItemQuery {
+            status,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_filters(mut self, filters: Vec<Filter>) -> Self {
+        self.filters = filters;
+        self
+    }
+
+    pub fn with_sort(mut self, sort: Vec<SortKey>) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    pub fn with_limit(mut self, limit: Option<u32>) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn with_offset(mut self, offset: u32) -> Self {
+        self.offset = offset;
+        self
+    }
+}
+
+/// Field names which can be used for sorting besides the schema fields
+pub const SORT_ID: &str = "id";
+pub const SORT_CREATED: &str = "created";
+pub const SORT_DONE: &str = "done";
+
+/// Sort key in form `field[:asc|:desc]`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SortKey {
+    pub field: String,
+    pub descending: bool,
+}
+
+impl FromStr for SortKey {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (field, descending) = match s.rsplit_once(':') {
+            Some((field, "desc")) => (field, true),
+            Some((field, "asc")) => (field, false),
+            Some((_, order)) => return Err(anyhow!("Unknown sort order '{order}'")),
+            None => (s, false),
+        };
+        if field.is_empty() {
+            return Err(anyhow!("Field name is missing in '{s}'"));
+        }
+        Ok(SortKey {
+            field: field.to_string(),
+            descending,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// Regular expression match
+    Matches,
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let op = match self {
+            Operator::Eq => "=",
+            Operator::Ne => "!=",
+            Operator::Lt => "<",
+            Operator::Le => "<=",
+            Operator::Gt => ">",
+            Operator::Ge => ">=",
+            Operator::Matches => "~",
+        };
+        write!(f, "{op}")
+    }
+}
+
+/// Condition on a field value, e.g. `pages>=300` or `title~^The`. The value is checked against
+/// the field type when the filter is applied.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Filter {
+    pub field: String,
+    pub op: Operator,
+    pub value: String,
+}
+
+impl FromStr for Filter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let idx = s
+            .find(['=', '!', '<', '>', '~'])
+            .ok_or_else(|| anyhow!("'{s}' must be in form <field><operator><value>"))?;
+        let (field, rest) = s.split_at(idx);
+        let (op, len) = match (rest.as_bytes()[0], rest.as_bytes().get(1)) {
+            (b'!', Some(b'=')) => (Operator::Ne, 2),
+            (b'<', Some(b'=')) => (Operator::Le, 2),
+            (b'>', Some(b'=')) => (Operator::Ge, 2),
+            (b'=', _) => (Operator::Eq, 1),
+            (b'<', _) => (Operator::Lt, 1),
+            (b'>', _) => (Operator::Gt, 1),
+            (b'~', _) => (Operator::Matches, 1),
+            _ => return Err(anyhow!("Unknown operator in '{s}'")),
+        };
+        let field = field.trim();
+        if field.is_empty() {
+            return Err(anyhow!("Field name is missing in '{s}'"));
+        }
+        Ok(Filter {
+            field: field.to_string(),
+            op,
+            // Spaces around the operator are allowed, so `author = Tolkien` works for text
+            value: rest[len..].trim().to_string(),
+        })
+    }
+}
+
+impl Display for Filter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}{}", self.field, self.op, self.value)
+    }
+}
+
+#[derive(Debug)]
+pub struct FoundItem {
+    pub item: DbItem,
+    /// Byte ranges of the matched text for each item field. Empty for fields without matches.
+    pub matches: Vec<Vec<Range<usize>>>,
+}
+
+/// Additional rules for random draws
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct DrawOptions {
+    /// Choose a value of this field first, and then an item with this value
+    pub balance_by: Option<String>,
+    /// Avoid items sharing field values with the recently drawn ones
+    pub cooldown: Vec<Cooldown>,
+    /// Only draw items with effort estimate not exceeding this number of minutes
+    pub max_effort: Option<u32>,
+}
+
+impl DrawOptions {
+    pub fn with_balance_by(mut self, field: Option<String>) -> Self {
+        self.balance_by = field;
+        self
+    }
+
+    pub fn with_cooldown(mut self, cooldown: Vec<Cooldown>) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    pub fn with_max_effort(mut self, minutes: Option<u32>) -> Self {
+        self.max_effort = minutes;
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Cooldown {
+    pub field: String,
+    /// Number of recent draws to check
+    pub draws: u32,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub struct DbSchema {
     pub fields: Vec<DbFieldDesc>,
+    /// Number field holding effort estimates in minutes
+    pub effort_field: Option<String>,
+}
+
+/// Names used by the storage, which cannot be field names
+pub const RESERVED_FIELD_NAMES: &[&str] = &["id", "done_at", "rowid", "rank", "items_fts"];
+
+/// Prefix of the internal column names, which cannot start field names
+pub const RESERVED_FIELD_PREFIX: &str = "_rn_";
+
+impl DbSchema {
+    pub fn new(fields: Vec<DbFieldDesc>) -> Self {
+        DbSchema {
+            fields,
+            effort_field: None,
+        }
+    }
+
+    pub fn with_effort_field(mut self, field: Option<String>) -> Self {
+        self.effort_field = field;
+        self
+    }
+
+    /// Checks that the schema has fields, their names are valid and unique, and the effort field
+    /// is one of the number fields. Names are compared ignoring case, same as in SQL.
+    pub fn validate(&self) -> Result<()> {
+        if self.fields.is_empty() {
+            return Err(invalid("Schema must have at least one field".to_string()));
+        }
+        for (i, field) in self.fields.iter().enumerate() {
+            validate_field_name(&field.name)?;
+            if self.fields[..i]
+                .iter()
+                .any(|f| f.name.eq_ignore_ascii_case(&field.name))
+            {
+                return Err(invalid(format!("Duplicate field name {}", field.name)));
+            }
+        }
+        if let Some(effort) = &self.effort_field
+            && !self
+                .fields
+                .iter()
+                .any(|f| f.name == *effort && f.field_type == DbFieldType::Number)
+        {
+            return Err(invalid(format!(
+                "Effort field {effort} must be a number field"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Checks that the field name is not empty and doesn't collide with the reserved names.
+pub fn validate_field_name(name: &str) -> Result<()> {
+    if name.trim().is_empty() {
+        Err(invalid("Field name cannot be empty".to_string()))
+    } else if name.chars().any(char::is_control) {
+        Err(invalid(format!(
+            "Field name {name:?} cannot contain control characters"
+        )))
+    } else if RESERVED_FIELD_NAMES
+        .iter()
+        .any(|r| r.eq_ignore_ascii_case(name))
+        || name
+            .get(..RESERVED_FIELD_PREFIX.len())
+            .is_some_and(|p| p.eq_ignore_ascii_case(RESERVED_FIELD_PREFIX))
+    {
+        Err(invalid(format!("Field name {name} is reserved")))
+    } else {
+        Ok(())
+    }
 }
 
-#[derive(Clone)]
+fn invalid(message: String) -> anyhow::Error {
+    RednextError::InvalidInput(message).into()
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DbFieldDesc {
     pub name: String,
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
     pub field_type: DbFieldType,
 }
 
@@ -45,7 +483,9 @@ impl DbFieldDesc {
     }
 }
 
-#[derive(Clone, EnumString, Display, PartialEq, Eq)]
+#[derive(Clone, Debug, EnumString, Display, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum DbFieldType {
     Text,
     Number,
@@ -53,12 +493,19 @@ pub enum DbFieldType {
     DateTime,
 }
 
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DbField {
     pub name: String,
     pub value: DbValue,
 }
 
+/// Field value. With the `serde` feature it is serialized with its type, e.g.
+/// `{"type": "Number", "value": 310}`, and timestamps are ISO 8601 strings without time zone.
 #[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "value"))]
+#[non_exhaustive]
 pub enum DbValue {
     Text(String),
     Number(i32),
@@ -66,10 +513,43 @@ pub enum DbValue {
     DateTime(NaiveDateTime),
 }
 
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub struct DbItem {
     pub id: u32,
     pub fields: Vec<DbField>,
+    /// Creation time, unknown for items created by old versions
+    pub created_at: Option<NaiveDateTime>,
     pub completed_at: Option<NaiveDateTime>,
+    /// Item has prerequisites which are not done yet
+    pub blocked: bool,
+    /// Item is in the queue of pinned items
+    pub pinned: bool,
+}
+
+impl DbItem {
+    /// Creates item which is not done, blocked or pinned, and has no creation time.
+    pub fn new(id: u32, fields: Vec<DbField>) -> Self {
+        DbItem {
+            id,
+            fields,
+            created_at: None,
+            completed_at: None,
+            blocked: false,
+            pinned: false,
+        }
+    }
+
+    pub fn with_created_at(mut self, time: Option<NaiveDateTime>) -> Self {
+        self.created_at = time;
+        self
+    }
+
+    pub fn with_completed_at(mut self, time: Option<NaiveDateTime>) -> Self {
+        self.completed_at = time;
+        self
+    }
 }
 
 impl Display for DbValue {
@@ -82,3 +562,176 @@ impl Display for DbValue {
         }
     }
 }
+
+pub(crate) mod sealed {
+    pub trait Sealed {}
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::{
+        DbFieldDesc, DbFieldType, DbSchema, FileNotFound, Filter, Operator, SortKey, edit_distance,
+        similar_names,
+    };
+
+    #[test]
+    fn test_parse_filter() {
+        let filter = |s: &str| s.parse::<Filter>().unwrap();
+        assert_eq!(
+            filter("author=Tolkien"),
+            Filter {
+                field: "author".to_string(),
+                op: Operator::Eq,
+                value: "Tolkien".to_string(),
+            }
+        );
+        assert_eq!(filter("added>=2026-01-01").op, Operator::Ge);
+        assert_eq!(filter("pages<300").op, Operator::Lt);
+        assert_eq!(filter("read != true").field, "read");
+        assert_eq!(filter("read != true").value, "true");
+        assert_eq!(filter("author = Tolkien").value, "Tolkien");
+        assert_eq!(filter("title ~ ^The ").value, "^The");
+        assert_eq!(filter("title~^The .*=").value, "^The .*=");
+        assert_eq!(filter("book title>a").field, "book title");
+        assert_eq!(filter("pages>=300").to_string(), "pages>=300");
+        assert!("author".parse::<Filter>().is_err());
+        assert!("=Tolkien".parse::<Filter>().is_err());
+        assert!("author!Tolkien".parse::<Filter>().is_err());
+    }
+
+    #[test]
+    fn test_parse_sort_key() {
+        let key = |s: &str| s.parse::<SortKey>().unwrap();
+        assert_eq!(
+            key("pages:desc"),
+            SortKey {
+                field: "pages".to_string(),
+                descending: true,
+            }
+        );
+        assert!(!key("pages:asc").descending);
+        assert!(!key("pages").descending);
+        assert!("pages:up".parse::<SortKey>().is_err());
+        assert!(":desc".parse::<SortKey>().is_err());
+    }
+
+    #[test]
+    fn test_similar_names() {
+        assert_eq!(edit_distance("books", "books"), 0);
+        assert_eq!(edit_distance("boks", "books"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+
+        let files = ["books", "movies", "Boots", "games"].map(String::from);
+        assert_eq!(similar_names("boots", &files), vec!["Boots", "books"]);
+        assert_eq!(similar_names("BOKS", &files), vec!["books"]);
+        assert_eq!(similar_names("movie", &files), vec!["movies"]);
+        assert!(similar_names("podcasts", &files).is_empty());
+
+        assert_eq!(
+            FileNotFound::new("movie", &files).to_string(),
+            "File movie is not found. Did you mean movies?"
+        );
+        assert_eq!(
+            FileNotFound::new("x", &[]).to_string(),
+            "File x is not found"
+        );
+    }
+
+    #[test]
+    fn test_validate_schema() {
+        let schema = |names: &[&str], effort: Option<&str>| DbSchema {
+            fields: names
+                .iter()
+                .map(|n| DbFieldDesc::new(n, DbFieldType::Number))
+                .collect(),
+            effort_field: effort.map(|e| e.to_string()),
+        };
+        assert!(schema(&["title", "book \"name\""], None).validate().is_ok());
+        assert!(
+            schema(&["title", "minutes"], Some("minutes"))
+                .validate()
+                .is_ok()
+        );
+        assert!(schema(&[], None).validate().is_err());
+        assert!(schema(&["title", " "], None).validate().is_err());
+        assert!(schema(&["title", "Title"], None).validate().is_err());
+        assert!(schema(&["ID"], None).validate().is_err());
+        assert!(schema(&["done_at"], None).validate().is_err());
+        assert!(schema(&["_RN_pinned"], None).validate().is_err());
+        assert!(schema(&["_rn_blocked"], None).validate().is_err());
+        assert!(
+            schema(&["position", "pinned", "created_at", "hl0", "hit_id"], None)
+                .validate()
+                .is_ok()
+        );
+        assert!(schema(&["title"], Some("pages")).validate().is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        use chrono::NaiveDate;
+        use serde_json::json;
+
+        use crate::db::{DbField, DbItem, DbValue};
+
+        let schema = DbSchema {
+            fields: vec![
+                DbFieldDesc::new("title", DbFieldType::Text),
+                DbFieldDesc::new("minutes", DbFieldType::Number),
+            ],
+            effort_field: Some("minutes".to_string()),
+        };
+        assert_eq!(
+            serde_json::to_value(&schema).unwrap(),
+            json!({
+                "fields": [
+                    {"name": "title", "type": "Text"},
+                    {"name": "minutes", "type": "Number"}
+                ],
+                "effort_field": "minutes"
+            })
+        );
+
+        let time = NaiveDate::from_ymd_opt(2026, 3, 1)
+            .unwrap()
+            .and_hms_opt(10, 30, 0)
+            .unwrap();
+        let item = DbItem {
+            id: 7,
+            fields: vec![
+                DbField {
+                    name: "title".to_string(),
+                    value: DbValue::Text("Dune".to_string()),
+                },
+                DbField {
+                    name: "added".to_string(),
+                    value: DbValue::DateTime(time),
+                },
+            ],
+            created_at: Some(time),
+            completed_at: None,
+            blocked: false,
+            pinned: true,
+        };
+        let value = json!({
+            "id": 7,
+            "fields": [
+                {"name": "title", "value": {"type": "Text", "value": "Dune"}},
+                {"name": "added", "value": {"type": "DateTime", "value": "2026-03-01T10:30:00"}}
+            ],
+            "created_at": "2026-03-01T10:30:00",
+            "completed_at": null,
+            "blocked": false,
+            "pinned": true
+        });
+        assert_eq!(serde_json::to_value(&item).unwrap(), value);
+        let parsed: DbItem = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed.fields[1].value, DbValue::DateTime(time));
+        assert_eq!(
+            serde_json::from_value::<DbValue>(json!({"type": "Number", "value": 310})).unwrap(),
+            DbValue::Number(310)
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use std::fmt::{Display, Formatter};
+
+use crate::db::FileNotFound;
+
+/// Errors which callers may need to tell apart. Errors of other types are treated as storage
+/// failures. New variants may be added in minor releases.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum RednextError {
+    FileNotFound(FileNotFound),
+    ItemNotFound(u32),
+    /// Invalid argument or value entered by the user
+    InvalidInput(String),
+    /// Unknown field, or the field type doesn't fit the operation
+    SchemaMismatch(String),
+    /// File cannot be read or written
+    Storage(String),
+    /// Operation contradicts the current state of the file
+    Conflict(String),
+    /// There are no items which can be drawn, with the reason
+    NothingToDraw(String),
+}
+
+impl std::error::Error for RednextError {}
+
+impl Display for RednextError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RednextError::FileNotFound(e) => write!(f, "{e}"),
+            RednextError::ItemNotFound(id) => write!(f, "Item with id {id} is not found"),
+            RednextError::InvalidInput(msg)
+            | RednextError::SchemaMismatch(msg)
+            | RednextError::Storage(msg)
+            | RednextError::Conflict(msg)
+            | RednextError::NothingToDraw(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+impl From<FileNotFound> for RednextError {
+    fn from(e: FileNotFound) -> Self {
+        RednextError::FileNotFound(e)
+    }
+}
+
+impl RednextError {
+    /// Process exit code for the error category.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            RednextError::Storage(_) => 1,
+            RednextError::FileNotFound(_) | RednextError::ItemNotFound(_) => 3,
+            RednextError::SchemaMismatch(_) => 4,
+            RednextError::Conflict(_) => 5,
+            RednextError::NothingToDraw(_) => 6,
+            // 2 is used by clap for usage errors
+            RednextError::InvalidInput(_) => 7,
+        }
+    }
+}
+
+/// Returns exit code for the error, which can also be a context of another error.
+pub fn exit_code(err: &anyhow::Error) -> u8 {
+    const STORAGE: u8 = 1;
+    err.downcast_ref::<RednextError>()
+        .map_or(STORAGE, RednextError::exit_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{Context, anyhow};
+
+    use crate::error::{RednextError, exit_code};
+
+    #[test]
+    fn test_exit_code() {
+        let err = anyhow::Error::from(RednextError::ItemNotFound(5)).context("Cannot show item");
+        assert_eq!(exit_code(&err), 3);
+        assert_eq!(
+            format!("{err:#}"),
+            "Cannot show item: Item with id 5 is not found"
+        );
+
+        let err = "x"
+            .parse::<i32>()
+            .context(RednextError::InvalidInput("Invalid number x".to_string()))
+            .unwrap_err();
+        assert_eq!(exit_code(&err), 7);
+
+        let err = anyhow!("Cannot open DB").context(RednextError::Conflict("Busy".to_string()));
+        assert_eq!(exit_code(&err), 5);
+        assert_eq!(exit_code(&anyhow!("Cannot open DB")), 1);
+    }
+}
@@ -0,0 +1,38 @@
+//! Random task lists stored in SQLite files.
+//!
+//! A [`db::DB`] is a collection of files, and a [`db::DBFile`] is a list of items with the
+//! fields described by its [`db::DbSchema`]. [`sqlite::SqliteDB`] keeps every file as a separate
+//! SQLite database in a directory.
+//!
+//! ```no_run
+//! use std::path::Path;
+//!
+//! use rednext::{
+//!     db::{DB, DrawOptions},
+//!     sqlite::SqliteDB,
+//! };
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let db = SqliteDB::new(Path::new("/home/user/.config/rednext"));
+//! let file = db.open("books")?;
+//! let mut rng = rand::make_rng::<rand::rngs::ChaCha8Rng>();
+//! if let Some(item) = file.get_random(&mut rng, &DrawOptions::default())? {
+//!     println!("Read next: {}", item.fields[0].value);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Errors are [`anyhow::Error`], and the ones callers may want to handle are
+//! [`error::RednextError`] values, which can be found with `downcast_ref`.
+//!
+//! Types which may get new fields or variants are `#[non_exhaustive]`, so structs are created
+//! with their constructors and `with_*` methods. The storage traits are sealed and can't be
+//! implemented outside of the crate.
+
+pub mod db;
+pub mod error;
+pub mod sqlite;
+
+/// Random number generators accepted by the draw methods come from this version of `rand`.
+pub use rand;
@@ -1,31 +1,53 @@
 use std::{
     error::Error,
     fmt::Display,
+    io::{self, Write},
+    iter,
     path::{Path, PathBuf},
+    process::ExitCode,
     str::FromStr,
+    time::Duration,
 };
 
 use anyhow::Context;
-use chrono::{Local, NaiveDate, NaiveDateTime};
+use chrono::{Local, NaiveDate, NaiveDateTime, TimeDelta};
 use clap::{Args, Parser, Subcommand, ValueEnum};
-use comfy_table::Table;
-use console::Style;
+use console::{Style, Term};
 use csv::{ReaderBuilder, Trim};
 use dialoguer::{Confirm, Input, Select};
-use dirs::config_dir;
+use rand::{Rng, RngExt, SeedableRng, rngs::ChaCha8Rng};
+use serde::{Deserialize, Serialize};
+
+use rednext::{
+    db::{
+        Cooldown, DB, DBFile, DbField, DbFieldDesc, DbFieldType, DbItem, DbSchema, DbValue,
+        DrawOptions, Filter, FoundItem, ItemQuery, ItemStatus, SortKey, validate_field_name,
+    },
+    error::{self, RednextError},
+    sqlite::{DEFAULT_BUSY_TIMEOUT, SqliteDB},
+};
 
 use crate::{
-    db::{DB, DBFile, DbField, DbFieldDesc, DbFieldType, DbItem, DbSchema, DbValue},
-    sqlite::SqliteDB,
+    config::{CONFIG_FILE, Config},
+    output::StreamTable,
+    report::Aggregate,
+    stats::{PeriodStats, Stats},
 };
 
-mod db;
-mod sqlite;
+mod chart;
+mod config;
+mod output;
+mod report;
+mod stats;
 
 #[derive(Subcommand, Debug)]
 enum Action {
-    /// List available files
-    List,
+    /// List available files with their progress
+    List {
+        /// Output format. Default is table, unless set in the config file.
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+    },
 
     /// Operations within items
     Items(ItemsParams),
@@ -49,6 +71,37 @@ enum Action {
     /// Delete file
     #[command(arg_required_else_help = true)]
     Delete { name: String },
+
+    /// Get random item from several files
+    Next {
+        /// Files to choose from. The default file from the config is used if omitted.
+        files: Vec<String>,
+
+        /// Choose from all files
+        #[clap(long, conflicts_with = "files")]
+        all: bool,
+
+        /// Relative chance to choose the file, e.g. books=2. Default is 1.
+        #[clap(long, value_parser=weight_parser)]
+        weight: Vec<(String, u32)>,
+
+        /// Seed for reproducible draws
+        #[clap(long)]
+        seed: Option<u64>,
+    },
+
+    /// Find items in all files. Files are upgraded to the current format and indexed for
+    /// search, like when they are opened by any other command.
+    #[command(arg_required_else_help = true)]
+    Find {
+        /// Text to search for
+        text: Option<String>,
+
+        /// Search only in the given field, or filter by value, e.g. author=Tolkien,
+        /// pages>=300, added<2026-01-01, read=false or title~^The. Can be used several times.
+        #[clap(long, value_parser=field_arg_parser)]
+        field: Vec<FieldArg>,
+    },
 }
 
 #[derive(Debug, Args)]
@@ -68,6 +121,61 @@ enum ItemsAction {
             value_enum
         )]
         what: ListWhat,
+
+        /// Show only items matching the filter, e.g. pages>=300. Can be used several times.
+        #[clap(long, value_parser=filter_parser)]
+        field: Vec<Filter>,
+
+        /// Sort by field, e.g. pages:desc. Fields id, created and done are also available.
+        /// Several keys can be separated by commas.
+        #[clap(long, value_parser=sort_parser, value_delimiter=',')]
+        sort: Vec<SortKey>,
+
+        /// Comma separated columns to show. Columns id and done are also available.
+        #[clap(long, value_delimiter = ',')]
+        columns: Vec<String>,
+
+        /// Comma separated columns to hide
+        #[clap(long, value_delimiter = ',')]
+        hide: Vec<String>,
+
+        /// Maximum number of items to show
+        #[clap(long)]
+        limit: Option<u32>,
+
+        /// Number of items to skip
+        #[clap(long, default_value_t = 0)]
+        offset: u32,
+
+        /// Show the page with the given number. Page size depends on the terminal height.
+        #[clap(long, conflicts_with_all = ["limit", "offset"], value_parser = clap::value_parser!(u32).range(1..))]
+        page: Option<u32>,
+
+        /// Output format. Items are written as they are read, so big lists are shown without
+        /// delay.
+        #[arg(long, value_enum, default_value_t = ListFormat::Table)]
+        format: ListFormat,
+    },
+
+    /// Show completion statistics
+    Stats,
+
+    /// Draw completion charts
+    Chart,
+
+    /// Show number of items and completion by groups
+    Report {
+        /// Field to group items by. Timestamps are grouped by month.
+        #[clap(long)]
+        group_by: String,
+
+        /// Number field to sum up in each group
+        #[clap(long)]
+        sum: Vec<String>,
+
+        /// Number field to calculate average in each group
+        #[clap(long)]
+        avg: Vec<String>,
     },
 
     /// Interactively add a new item
@@ -91,13 +199,102 @@ enum ItemsAction {
     },
 
     /// Find item by name
-    Find { name: String },
+    #[command(arg_required_else_help = true)]
+    Find {
+        name: Option<String>,
+
+        /// Search only in the given field, or filter by value, e.g. author=Tolkien,
+        /// pages>=300, added<2026-01-01, read=false or title~^The. Can be used several times.
+        #[clap(long, value_parser=field_arg_parser)]
+        field: Vec<FieldArg>,
+    },
 
-    /// Get item by ID
+    /// Get item by ID and mark it as done or undone
     Get { id: u32 },
 
+    /// Show item details without changing it
+    Show { id: u32 },
+
+    /// Mark item as done
+    Done {
+        id: u32,
+
+        /// Completion time, e.g. "2026-03-01" or "2026-03-01 18:30:00". Default is now.
+        #[clap(long)]
+        at: Option<Date>,
+    },
+
+    /// Mark item as not done
+    Undone { id: u32 },
+
+    /// Make item depend on another one. The item will not be drawn until
+    /// all its prerequisites are done.
+    Depend {
+        /// Dependent item ID
+        id: u32,
+        /// Prerequisite item ID
+        prerequisite: u32,
+    },
+
+    /// Remove dependency between items
+    Undepend {
+        /// Dependent item ID
+        id: u32,
+        /// Prerequisite item ID
+        prerequisite: u32,
+    },
+
+    /// Add item to the end of the pinned queue. Pinned items are served before
+    /// any other ones.
+    Pin { id: u32 },
+
+    /// Remove item from the pinned queue
+    Unpin { id: u32 },
+
+    /// Move item to a new position in the manual order
+    Move {
+        /// Item ID
+        id: u32,
+        /// New position, starting from 1
+        position: u32,
+    },
+
     /// Get random item
-    GetRandom,
+    GetRandom {
+        /// Seed for reproducible draws
+        #[clap(long)]
+        seed: Option<u64>,
+
+        /// How to choose the item. Default is random, unless set in the config file.
+        #[arg(long, value_enum)]
+        strategy: Option<Strategy>,
+
+        /// Field to order items by in the sequential mode. Manual order is used by default.
+        #[clap(long)]
+        order_by: Option<String>,
+
+        /// Choose a value of the field first, and then an item with this value
+        #[clap(long)]
+        balance_by: Option<String>,
+
+        /// Avoid values of the field drawn in the last N draws, e.g. genre=3
+        #[clap(long, value_parser=cooldown_parser)]
+        cooldown: Vec<Cooldown>,
+
+        /// Only draw items which fit into the given time, e.g. 30m, 2h or 1h30m
+        #[clap(long, value_parser=effort_parser)]
+        max_effort: Option<u32>,
+    },
+
+    /// Set number field holding effort estimates in minutes
+    SetEffort {
+        /// Field name. If omitted, the file will have no effort field.
+        field: Option<String>,
+    },
+
+    /// Get item of the day. The choice depends on the date and the file name only,
+    /// so everybody with the same list gets the same item.
+    Today,
 }
 
 #[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
@@ -105,6 +302,50 @@ enum ListWhat {
     All,
     Done,
     Undone,
+    Pinned,
+}
+
+/// Output format of the item list
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum ListFormat {
+    Table,
+    Csv,
+    /// JSON object per line
+    Jsonl,
+}
+
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum OutputFormat {
+    Table,
+    Json,
+}
+
+impl std::fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.to_possible_value()
+            .expect("no values are skipped")
+            .get_name()
+            .fmt(f)
+    }
+}
+
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum Strategy {
+    /// Random item
+    Random,
+    /// First item in order
+    Sequential,
+}
+
+impl std::fmt::Display for Strategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.to_possible_value()
+            .expect("no values are skipped")
+            .get_name()
+            .fmt(f)
+    }
 }
 
 impl std::fmt::Display for ListWhat {
@@ -116,9 +357,52 @@ impl std::fmt::Display for ListWhat {
     }
 }
 
+impl From<ListWhat> for ItemStatus {
+    fn from(what: ListWhat) -> Self {
+        match what {
+            ListWhat::All => ItemStatus::All,
+            ListWhat::Done => ItemStatus::Done,
+            ListWhat::Undone => ItemStatus::Undone,
+            ListWhat::Pinned => ItemStatus::Pinned,
+        }
+    }
+}
+
+/// Field to search in, or filter by field value
+#[derive(Clone, Debug)]
+enum FieldArg {
+    Name(String),
+    Filter(Filter),
+}
+
+/// Splits field arguments into field names and filters.
+fn split_field_args(args: Vec<FieldArg>) -> (Vec<String>, Vec<Filter>) {
+    let mut names = Vec::new();
+    let mut filters = Vec::new();
+    for arg in args {
+        match arg {
+            FieldArg::Name(name) => names.push(name),
+            FieldArg::Filter(filter) => filters.push(filter),
+        }
+    }
+    (names, filters)
+}
+
 #[derive(Parser, Debug)]
 #[command(about = "Simple random tasks manager")]
 struct Params {
+    /// Directory with the files. Has priority over the profile.
+    #[arg(long, global = true, env = "REDNEXT_DIR")]
+    db_dir: Option<PathBuf>,
+
+    /// Profile from the config file
+    #[arg(long, global = true, env = "REDNEXT_PROFILE")]
+    profile: Option<String>,
+
+    /// Milliseconds to wait while another process is writing to the file
+    #[arg(long, global = true, env = "REDNEXT_BUSY_TIMEOUT", value_name = "MS")]
+    busy_timeout: Option<u64>,
+
     #[command(subcommand)]
     action: Action,
 }
@@ -142,27 +426,215 @@ fn delimiter_parser(s: &str) -> Result<u8, String> {
     }
 }
 
-fn main() {
+/// Parses `<name>=<number>` pairs.
+fn key_number_parser(s: &str) -> Result<(String, u32), String> {
+    let (key, number) = s
+        .rsplit_once('=')
+        .ok_or(format!("'{s}' must be in form <name>=<number>"))?;
+    let number = number
+        .parse()
+        .map_err(|_| format!("Invalid number '{number}'"))?;
+    Ok((key.to_string(), number))
+}
+
+fn cooldown_parser(s: &str) -> Result<Cooldown, String> {
+    let (field, draws) = key_number_parser(s)?;
+    Ok(Cooldown { field, draws })
+}
+
+fn filter_parser(s: &str) -> Result<Filter, String> {
+    s.parse().map_err(|e: anyhow::Error| e.to_string())
+}
+
+fn sort_parser(s: &str) -> Result<SortKey, String> {
+    s.parse().map_err(|e: anyhow::Error| e.to_string())
+}
+
+fn field_arg_parser(s: &str) -> Result<FieldArg, String> {
+    if s.contains(['=', '!', '<', '>', '~']) {
+        filter_parser(s).map(FieldArg::Filter)
+    } else {
+        Ok(FieldArg::Name(s.to_string()))
+    }
+}
+
+fn weight_parser(s: &str) -> Result<(String, u32), String> {
+    key_number_parser(s)
+}
+
+/// Parses duration in minutes. Plain numbers are minutes as well.
+fn effort_parser(s: &str) -> Result<u32, String> {
+    let err = || format!("Invalid duration '{s}', expected something like 45, 30m, 2h or 1h30m");
+    if let Ok(minutes) = s.parse() {
+        return Ok(minutes);
+    }
+    if s.is_empty() {
+        return Err(err());
+    }
+    let (hours, rest) = match s.split_once('h') {
+        Some((h, rest)) => (h.parse::<u32>().map_err(|_| err())?, rest),
+        None => (0, s),
+    };
+    let minutes = match rest.strip_suffix('m') {
+        Some(m) => m.parse::<u32>().map_err(|_| err())?,
+        None if rest.is_empty() => 0,
+        None => return Err(err()),
+    };
+    hours
+        .checked_mul(60)
+        .and_then(|h| h.checked_add(minutes))
+        .ok_or_else(err)
+}
+
+/// Reads the config file and finds directory with the files.
+fn load_config(params: &Params) -> anyhow::Result<(Config, PathBuf)> {
+    let config = match config::default_dir() {
+        Ok(dir) => Config::load(&dir.join(CONFIG_FILE))?,
+        Err(_) => Config::default(),
+    };
+    let db_dir = config.db_dir(params.db_dir.as_deref(), params.profile.as_deref())?;
+    Ok((config, db_dir))
+}
+
+fn main() -> ExitCode {
     let params = Params::parse();
-    let mut db_path = config_dir().unwrap();
-    db_path.push("rednext");
-    let db = SqliteDB::new(&db_path);
+    match run(params) {
+        Ok(()) => ExitCode::SUCCESS,
+        // Output is written as it goes, so the reader may stop early, e.g. `head`
+        Err(e) if is_broken_pipe(&e) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e:#}");
+            ExitCode::from(error::exit_code(&e))
+        }
+    }
+}
+
+fn is_broken_pipe(e: &anyhow::Error) -> bool {
+    e.chain()
+        .filter_map(|e| e.downcast_ref::<io::Error>())
+        .any(|e| e.kind() == io::ErrorKind::BrokenPipe)
+}
+
+fn run(params: Params) -> anyhow::Result<()> {
+    let (config, db_path) = load_config(&params)?;
+    let busy_timeout = params
+        .busy_timeout
+        .or(config.busy_timeout)
+        .map_or(DEFAULT_BUSY_TIMEOUT, Duration::from_millis);
+    let db = SqliteDB::new(&db_path).with_busy_timeout(busy_timeout);
     match params.action {
-        Action::List => list(&db),
+        Action::List { format } => list(&db, format.unwrap_or(config.format), &config),
         Action::Items(ip) => {
-            let file = db.open(&ip.name).unwrap();
+            let mut file = db.open(&ip.name)?;
+            let file_config = config.file(&ip.name);
             match ip.action {
-                ItemsAction::List { what } => list_items(file.as_ref(), what),
+                ItemsAction::List {
+                    what,
+                    field,
+                    sort,
+                    columns,
+                    hide,
+                    limit,
+                    offset,
+                    page,
+                    format,
+                } => list_items(
+                    file.as_ref(),
+                    ItemQuery::status(what.into())
+                        .with_filters(field)
+                        .with_sort(sort)
+                        .with_limit(limit)
+                        .with_offset(offset),
+                    &columns,
+                    &hide,
+                    page,
+                    format,
+                    &config,
+                ),
+                ItemsAction::Stats => show_stats(file.as_ref(), &config),
+                ItemsAction::Chart => show_charts(file.as_ref()),
+                ItemsAction::Report { group_by, sum, avg } => {
+                    let aggregates = sum
+                        .into_iter()
+                        .map(Aggregate::Sum)
+                        .chain(avg.into_iter().map(Aggregate::Avg))
+                        .collect::<Vec<_>>();
+                    show_report(file.as_ref(), &group_by, &aggregates, &config)
+                }
                 ItemsAction::Add => add_item(file.as_ref()),
                 ItemsAction::Delete { id } => delete_item(file.as_ref(), id),
                 ItemsAction::Import {
                     file_name,
                     delimiter,
                     no_header,
-                } => import_csv(file.as_ref(), &file_name, no_header, delimiter).unwrap(),
-                ItemsAction::Get { id } => get(file.as_ref(), id),
-                ItemsAction::GetRandom => get_random(file.as_ref()),
-                ItemsAction::Find { name } => find_by_name(file.as_ref(), &name),
+                } => import_csv(file.as_ref(), &file_name, no_header, delimiter),
+                ItemsAction::Get { id } => get(file.as_ref(), id, &config),
+                ItemsAction::Show { id } => show_item(file.as_ref(), id, &config),
+                ItemsAction::Done { id, at } => {
+                    set_done(file.as_ref(), id, at.map(|d| d.0), &config)
+                }
+                ItemsAction::Undone { id } => set_undone(file.as_ref(), id),
+                ItemsAction::GetRandom {
+                    seed,
+                    strategy,
+                    order_by,
+                    balance_by,
+                    cooldown,
+                    max_effort,
+                } => match strategy
+                    .or(file_config.strategy)
+                    .unwrap_or(Strategy::Random)
+                {
+                    Strategy::Random => {
+                        if order_by.is_some() {
+                            return Err(RednextError::InvalidInput(
+                                "--order-by cannot be used with the random strategy".to_string(),
+                            )
+                            .into());
+                        }
+                        get_random(
+                            file.as_ref(),
+                            seed,
+                            &DrawOptions::default()
+                                .with_balance_by(balance_by.or(file_config.balance_by.clone()))
+                                .with_cooldown(cooldown)
+                                .with_max_effort(max_effort),
+                            &config,
+                        )
+                    }
+                    Strategy::Sequential => {
+                        // The strategy can come from the config, so clap can't check it
+                        let random_only = [
+                            ("--seed", seed.is_some()),
+                            ("--balance-by", balance_by.is_some()),
+                            ("--cooldown", !cooldown.is_empty()),
+                        ];
+                        if let Some((flag, _)) = random_only.iter().find(|(_, used)| *used) {
+                            return Err(RednextError::InvalidInput(format!(
+                                "{flag} cannot be used with the sequential strategy"
+                            ))
+                            .into());
+                        }
+                        get_next(
+                            file.as_ref(),
+                            order_by.or(file_config.order_by.clone()).as_deref(),
+                            max_effort,
+                            &config,
+                        )
+                    }
+                },
+                ItemsAction::Today => get_today(file.as_ref(), &ip.name, &config),
+                ItemsAction::Find { name, field } => {
+                    find_by_name(file.as_ref(), name.as_deref(), field)
+                }
+                ItemsAction::Depend { id, prerequisite } => file.add_dependency(id, prerequisite),
+                ItemsAction::Undepend { id, prerequisite } => {
+                    file.remove_dependency(id, prerequisite)
+                }
+                ItemsAction::SetEffort { field } => file.set_effort_field(field.as_deref()),
+                ItemsAction::Pin { id } => file.pin(id),
+                ItemsAction::Unpin { id } => file.unpin(id),
+                ItemsAction::Move { id, position } => file.move_to(id, position),
             }
         }
         Action::New {
@@ -170,58 +642,466 @@ fn main() {
             from_file,
             delimiter,
             no_header,
-        } => new_file(&db, &name, from_file, delimiter, no_header).unwrap(),
+        } => new_file(&db, &name, from_file, delimiter, no_header),
         Action::Delete { name } => delete(&db, &name),
+        Action::Next {
+            files,
+            all,
+            weight,
+            seed,
+        } => next(&db, files, all, &weight, seed, &config),
+        Action::Find { text, field } => find_everywhere(&db, text.as_deref(), field),
     }
 }
 
-fn list(db: &impl DB) {
-    let files = db.list_files().unwrap();
-    for (i, name) in (1..).zip(files) {
-        println!("{}. {}", i, name);
+#[derive(Serialize)]
+struct FileInfo {
+    name: String,
+    items: u32,
+    done: u32,
+    percent: f64,
+    last_activity: Option<String>,
+    /// Same as `last_activity`, for the output with the configured format
+    #[serde(skip)]
+    last_activity_time: Option<NaiveDateTime>,
+    fields: Vec<FieldInfo>,
+}
+
+#[derive(Serialize)]
+struct FieldInfo {
+    name: String,
+    #[serde(rename = "type")]
+    field_type: String,
+}
+
+/// Row of the files list. A broken file doesn't hide the other ones.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum FileEntry {
+    Info(FileInfo),
+    Failed { name: String, error: String },
+}
+
+fn list(db: &impl DB, format: OutputFormat, config: &Config) -> anyhow::Result<()> {
+    let files = db
+        .list_files()?
+        .into_iter()
+        .map(|name| match db.summary(&name) {
+            Ok((schema, summary)) => FileEntry::Info(FileInfo {
+                name,
+                items: summary.items,
+                done: summary.done,
+                percent: if summary.items > 0 {
+                    (summary.done as f64) / (summary.items as f64) * 100.0
+                } else {
+                    0.0
+                },
+                last_activity: summary
+                    .last_activity
+                    .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string()),
+                last_activity_time: summary.last_activity,
+                fields: schema
+                    .fields
+                    .into_iter()
+                    .map(|f| FieldInfo {
+                        name: f.name,
+                        field_type: f.field_type.to_string(),
+                    })
+                    .collect(),
+            }),
+            Err(e) => FileEntry::Failed {
+                name,
+                error: format!("{e:#}"),
+            },
+        })
+        .collect::<Vec<_>>();
+
+    match format {
+        OutputFormat::Table => {
+            let mut table = config.table();
+            table.set_header(["Name", "Items", "Done", "%", "Last activity", "Fields"]);
+            for entry in files {
+                let row = match entry {
+                    FileEntry::Info(f) => {
+                        let fields = f
+                            .fields
+                            .iter()
+                            .map(|fi| format!("{} ({})", fi.name, fi.field_type))
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        [
+                            f.name,
+                            f.items.to_string(),
+                            f.done.to_string(),
+                            format!("{:.2}", f.percent),
+                            f.last_activity_time
+                                .map(|dt| config.format_time(dt))
+                                .unwrap_or_default(),
+                            fields,
+                        ]
+                    }
+                    FileEntry::Failed { name, error } => {
+                        let empty = String::new;
+                        [
+                            name,
+                            empty(),
+                            empty(),
+                            empty(),
+                            empty(),
+                            format!("Error: {error}"),
+                        ]
+                    }
+                };
+                table.add_row(row);
+            }
+            writeln!(io::stdout(), "{table}")?;
+        }
+        OutputFormat::Json => writeln!(io::stdout(), "{}", serde_json::to_string_pretty(&files)?)?,
     }
+    Ok(())
+}
+
+/// Item listing column
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Column {
+    Id,
+    /// Field index in the schema
+    Field(usize),
+    Done,
 }
 
-fn list_items(file: &dyn DBFile, what: ListWhat) {
-    let items = match what {
-        ListWhat::All => file.list_items(),
-        ListWhat::Done => file.list_done(),
-        ListWhat::Undone => file.list_undone(),
+/// Returns listing columns. All columns are shown by default, `columns` sets them explicitly,
+/// and `hide` removes some of them.
+fn list_columns(
+    schema: &DbSchema,
+    columns: &[String],
+    hide: &[String],
+) -> anyhow::Result<Vec<Column>> {
+    let parse = |name: &String| match schema.fields.iter().position(|f| f.name == *name) {
+        Some(idx) => Ok(Column::Field(idx)),
+        None if name.eq_ignore_ascii_case("id") => Ok(Column::Id),
+        None if name.eq_ignore_ascii_case("done") => Ok(Column::Done),
+        None => Err(RednextError::SchemaMismatch(format!("Unknown column {name}")).into()),
+    };
+    let mut result = if columns.is_empty() {
+        iter::once(Column::Id)
+            .chain((0..schema.fields.len()).map(Column::Field))
+            .chain(iter::once(Column::Done))
+            .collect()
+    } else {
+        columns
+            .iter()
+            .map(parse)
+            .collect::<anyhow::Result<Vec<_>>>()?
+    };
+    let hidden = hide.iter().map(parse).collect::<anyhow::Result<Vec<_>>>()?;
+    result.retain(|c| !hidden.contains(c));
+    Ok(result)
+}
+
+fn list_items(
+    file: &dyn DBFile,
+    mut query: ItemQuery,
+    columns: &[String],
+    hide: &[String],
+    page: Option<u32>,
+    format: ListFormat,
+    config: &Config,
+) -> anyhow::Result<()> {
+    // Table borders, header, totals and the prompt
+    const LIST_OVERHEAD: usize = 6;
+
+    let schema = file.schema();
+    let columns = list_columns(&schema, columns, hide)?;
+    let mut pages = None;
+    if let Some(page) = page {
+        let (rows, _) = Term::stdout().size();
+        let page_size = (rows as usize).saturating_sub(LIST_OVERHEAD).max(1) as u32;
+        let count = file.count(&query)?;
+        query.limit = Some(page_size);
+        query.offset = (page.max(1) - 1)
+            .checked_mul(page_size)
+            .ok_or_else(|| RednextError::InvalidInput(format!("Page {page} is out of range")))?;
+        pages = Some(count.div_ceil(page_size).max(1));
     }
-    .unwrap();
+    let paginated = query.limit.is_some() || query.offset > 0;
+    // Machine readable formats use the same names as --columns
+    let keys = columns
+        .iter()
+        .map(|c| match c {
+            Column::Id => "id".to_string(),
+            Column::Field(idx) => schema.fields[*idx].name.clone(),
+            Column::Done => "done".to_string(),
+        })
+        .collect::<Vec<_>>();
+    let mut out = io::stdout().lock();
 
-    let stat_style = Style::new().bold();
-    let mut done_count = 0;
-    let total = items.len();
-    let mut table = Table::new();
-    let mut header = vec!["ID".to_string()];
-    file.schema().fields.into_iter().for_each(|f| {
-        header.push(f.name);
-    });
-    header.push("Done".to_string());
-    table.load_preset("││──╞═╪╡│    ┬┴┌┐└┘").set_header(header);
-    for i in items {
-        let done_str = i
-            .completed_at
-            .map_or("".to_string(), |dt| dt.format("%Y-%m-%d %H:%M").to_string());
-        let mut row = Vec::with_capacity(i.fields.len() + 2);
-        row.push(i.id.to_string());
-        row.extend(i.fields.iter().map(|f| f.value.to_string()));
-        row.push(done_str);
-        if i.completed_at.is_some() {
-            done_count += 1;
+    match format {
+        ListFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            writer.write_record(&keys)?;
+            file.for_each_item(&query, &mut |item| {
+                let row = columns.iter().map(|c| match c {
+                    Column::Done => item
+                        .completed_at
+                        .map(|dt| dt.to_string())
+                        .unwrap_or_default(),
+                    c => cell_text(&item, *c, config),
+                });
+                writer.write_record(row)?;
+                Ok(())
+            })?;
+            writer.flush()?;
+        }
+        ListFormat::Jsonl => {
+            file.for_each_item(&query, &mut |item| {
+                // Written by hand to keep the columns order
+                let fields = columns
+                    .iter()
+                    .zip(&keys)
+                    .map(|(c, key)| {
+                        format!(
+                            "{}:{}",
+                            serde_json::Value::from(key.as_str()),
+                            json_value(&item, *c)
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(",");
+                writeln!(out, "{{{fields}}}")?;
+                Ok(())
+            })?;
+        }
+        ListFormat::Table => {
+            let header = columns
+                .iter()
+                .zip(keys)
+                .map(|(c, key)| match c {
+                    Column::Id => "ID".to_string(),
+                    Column::Field(_) => key,
+                    Column::Done => "Done".to_string(),
+                })
+                .collect::<Vec<_>>();
+            let dim = Style::new().dim();
+            let mut widths = vec![0; columns.len()];
+            let mut done_count = 0;
+            let mut total = 0;
+            // Rows are measured first, so the table can be printed without keeping them. Both
+            // passes must see the same rows, even if another process changes the file in between.
+            file.read_snapshot(&mut || {
+                file.for_each_item(&query, &mut |item| {
+                    for (width, c) in widths.iter_mut().zip(&columns) {
+                        *width = (*width).max(output::text_width(&cell_text(&item, *c, config)));
+                    }
+                    total += 1;
+                    if item.completed_at.is_some() {
+                        done_count += 1;
+                    }
+                    Ok(())
+                })?;
+
+                let preset = config.table_style.preset();
+                let mut table = StreamTable::start(&mut out, preset, &header, &widths)?;
+                file.for_each_item(&query, &mut |item| {
+                    let row = columns
+                        .iter()
+                        .map(|c| cell_text(&item, *c, config))
+                        .collect::<Vec<_>>();
+                    let blocked = item.blocked && item.completed_at.is_none();
+                    table.row(&row, blocked.then_some(&dim))?;
+                    Ok(())
+                })?;
+                table.finish()?;
+                Ok(())
+            })?;
+
+            let stat_style = Style::new().bold();
+            if let (Some(page), Some(pages)) = (page, pages) {
+                writeln!(
+                    out,
+                    "{}",
+                    stat_style.apply_to(format!("Page {page} of {pages}"))
+                )?;
+            } else if query.status == ItemStatus::All && !paginated {
+                let stat = format!(
+                    "Total: done {} of {} ({:.2}%)",
+                    done_count,
+                    total,
+                    (done_count as f64) / (total as f64) * 100.0
+                );
+                writeln!(out, "{}", stat_style.apply_to(stat))?;
+            }
         }
+    }
+    Ok(())
+}
+
+/// Text of the item listing cell.
+fn cell_text(item: &DbItem, column: Column, config: &Config) -> String {
+    match column {
+        Column::Id => item.id.to_string(),
+        Column::Field(idx) => item.fields[idx].value.to_string(),
+        Column::Done => match item.completed_at {
+            Some(dt) => config.format_time(dt),
+            None if item.blocked => "blocked".to_string(),
+            None if item.pinned => "pinned".to_string(),
+            None => "".to_string(),
+        },
+    }
+}
+
+/// Value of the item listing cell in JSON. Typed values are kept, and timestamps are ISO 8601.
+fn json_value(item: &DbItem, column: Column) -> serde_json::Value {
+    let time = |dt: NaiveDateTime| dt.format("%Y-%m-%dT%H:%M:%S").to_string().into();
+    match column {
+        Column::Id => item.id.into(),
+        Column::Field(idx) => match &item.fields[idx].value {
+            DbValue::Text(s) => s.clone().into(),
+            DbValue::Number(n) => (*n).into(),
+            DbValue::Boolean(b) => (*b).into(),
+            DbValue::DateTime(dt) => time(*dt),
+            value => value.to_string().into(),
+        },
+        Column::Done => item.completed_at.map_or(serde_json::Value::Null, time),
+    }
+}
+
+fn show_stats(file: &dyn DBFile, config: &Config) -> anyhow::Result<()> {
+    let items = file.list_items()?;
+    let today = Local::now().date_naive();
+    let stats = Stats::calculate(&items, today);
+    let stat_style = Style::new().bold();
+
+    let percent = if stats.total > 0 {
+        (stats.done as f64) / (stats.total as f64) * 100.0
+    } else {
+        0.0
+    };
+    println!(
+        "{}",
+        stat_style.apply_to(format!(
+            "Total: done {} of {} ({:.2}%)",
+            stats.done, stats.total, percent
+        ))
+    );
+
+    let mut table = config.table();
+    table.set_header(["Completions", "Current", "Previous", "Average"]);
+    for (name, period) in [
+        ("Per day", &stats.days),
+        ("Per week", &stats.weeks),
+        ("Per month", &stats.months),
+    ] {
+        let PeriodStats {
+            current,
+            previous,
+            average,
+        } = period;
+        table.add_row([
+            name.to_string(),
+            current.to_string(),
+            previous.to_string(),
+            format!("{average:.2}"),
+        ]);
+    }
+    println!("{table}");
+
+    println!(
+        "Current streak: {} days, longest streak: {} days",
+        stats.current_streak, stats.longest_streak
+    );
+    if let Some(duration) = stats.average_completion {
+        println!("Average time to complete: {}", format_duration(duration));
+    }
+    if stats.done == stats.total {
+        println!("All items are complete");
+    } else if let Some(date) = stats.projected_finish {
+        println!("Projected finish at the current pace: {date}");
+    } else {
+        println!("Projected finish is unknown: nothing was done recently");
+    }
+    Ok(())
+}
+
+fn show_charts(file: &dyn DBFile) -> anyhow::Result<()> {
+    const CHART_HEIGHT: usize = 8;
+
+    let items = file.list_items()?;
+    let completions = items
+        .iter()
+        .filter_map(|i| i.completed_at.map(|dt| dt.date()))
+        .collect::<Vec<_>>();
+    if completions.is_empty() {
+        println!("Nothing is done yet");
+        return Ok(());
+    }
+
+    let today = Local::now().date_naive();
+    let (_, width) = Term::stdout().size();
+    let title_style = Style::new().bold();
+    println!("{}", title_style.apply_to("Completed items"));
+    for line in chart::cumulative(
+        &completions,
+        items.len(),
+        today,
+        width as usize,
+        CHART_HEIGHT,
+    ) {
+        println!("{line}");
+    }
+    println!();
+    println!("{}", title_style.apply_to("Completions per week"));
+    for line in chart::weekly(&completions, today, width as usize, CHART_HEIGHT) {
+        println!("{line}");
+    }
+    Ok(())
+}
+
+fn show_report(
+    file: &dyn DBFile,
+    group_by: &str,
+    aggregates: &[Aggregate],
+    config: &Config,
+) -> anyhow::Result<()> {
+    let items = file.list_items()?;
+    let rows = report::group_by(&file.schema(), &items, group_by, aggregates)?;
+
+    let mut table = config.table();
+    let mut header = vec![
+        group_by.to_string(),
+        "Items".to_string(),
+        "Done".to_string(),
+        "%".to_string(),
+    ];
+    header.extend(aggregates.iter().map(|a| a.to_string()));
+    table.set_header(header);
+    for r in rows {
+        let mut row = vec![
+            r.group.to_string(),
+            r.items.to_string(),
+            r.done.to_string(),
+            format!("{:.2}", r.percent()),
+        ];
+        row.extend(r.values.iter().zip(aggregates).map(|(v, a)| match a {
+            Aggregate::Sum(_) => v.to_string(),
+            Aggregate::Avg(_) => format!("{v:.2}"),
+        }));
         table.add_row(row);
     }
     println!("{table}");
-    if what == ListWhat::All {
-        let stat = format!(
-            "Total: done {} of {} ({:.2}%)",
-            done_count,
-            total,
-            (done_count as f64) / (total as f64) * 100.0
-        );
-        println!("{}", stat_style.apply_to(stat));
+    Ok(())
+}
+
+fn format_duration(duration: TimeDelta) -> String {
+    let days = duration.num_days();
+    let hours = duration.num_hours() % 24;
+    let minutes = duration.num_minutes() % 60;
+    if days > 0 {
+        format!("{days} days {hours} hours")
+    } else if hours > 0 {
+        format!("{hours} hours {minutes} minutes")
+    } else {
+        format!("{minutes} minutes")
     }
 }
 
@@ -230,7 +1110,7 @@ struct DateParseError;
 
 impl Error for DateParseError {}
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 struct Date(NaiveDateTime);
 
 impl FromStr for Date {
@@ -258,63 +1138,57 @@ impl Display for DateParseError {
     }
 }
 
-fn add_item(file: &dyn DBFile) {
+/// Error for field types added to the library after this version of the application.
+fn unsupported_type(field_type: &DbFieldType) -> anyhow::Error {
+    RednextError::SchemaMismatch(format!("Field type {field_type} is not supported")).into()
+}
+
+fn add_item(file: &dyn DBFile) -> anyhow::Result<()> {
     let mut fields = Vec::new();
     for field in file.schema().fields.iter() {
         let val = match field.field_type {
             DbFieldType::Text => {
-                let input: String = Input::new()
-                    .with_prompt(&field.name)
-                    .interact_text()
-                    .unwrap();
+                let input: String = Input::new().with_prompt(&field.name).interact_text()?;
                 DbValue::Text(input)
             }
             DbFieldType::Number => {
-                let input: i32 = Input::new()
-                    .with_prompt(&field.name)
-                    .interact_text()
-                    .unwrap();
+                let input: i32 = Input::new().with_prompt(&field.name).interact_text()?;
                 DbValue::Number(input)
             }
             DbFieldType::Boolean => {
-                let input: bool = Confirm::new().with_prompt(&field.name).interact().unwrap();
+                let input: bool = Confirm::new().with_prompt(&field.name).interact()?;
                 DbValue::Boolean(input)
             }
             DbFieldType::DateTime => {
-                let input: Date = Input::new()
-                    .with_prompt(&field.name)
-                    .interact_text()
-                    .unwrap();
+                let input: Date = Input::new().with_prompt(&field.name).interact_text()?;
                 DbValue::DateTime(input.0)
             }
+            _ => return Err(unsupported_type(&field.field_type)),
         };
         fields.push(DbField {
             name: field.name.clone(),
             value: val,
         });
     }
-    file.insert(&fields).unwrap();
+    file.insert(&fields, Local::now().naive_local())
 }
 
-fn delete_item(file: &dyn DBFile, id: u32) {
-    file.delete(id).unwrap();
+fn delete_item(file: &dyn DBFile, id: u32) -> anyhow::Result<()> {
+    file.delete(id)
 }
 
-fn get(file: &dyn DBFile, id: u32) {
-    if let Some(item) = file.get(id).unwrap() {
-        if item.completed_at.is_some() {
-            let conf = Confirm::new()
-                .with_prompt("Already done. Mark as undone?")
-                .interact()
-                .unwrap();
-            if conf {
-                file.undone(id).unwrap();
-            }
-        } else {
-            mark_done(file, item);
+fn get(file: &dyn DBFile, id: u32, config: &Config) -> anyhow::Result<()> {
+    let item = file.get(id)?.ok_or(RednextError::ItemNotFound(id))?;
+    if item.completed_at.is_some() {
+        let conf = Confirm::new()
+            .with_prompt("Already done. Mark as undone?")
+            .interact()?;
+        if conf {
+            file.undone(id)?;
         }
+        Ok(())
     } else {
-        println!("Item with id {id} doesn't exist");
+        mark_done(file, item, config)
     }
 }
 
@@ -326,40 +1200,395 @@ fn item_fields_to_string(item: &DbItem) -> String {
         .join(" - ")
 }
 
-fn get_random(file: &dyn DBFile) {
-    if let Some(item) = file.get_random().unwrap() {
+/// Same as `item_fields_to_string`, but with the matched text highlighted.
+fn highlight_matches(found: &FoundItem) -> String {
+    let style = Style::new().bold().yellow();
+    found
+        .item
+        .fields
+        .iter()
+        .zip(found.matches.iter())
+        .map(|(f, ranges)| {
+            let text = f.value.to_string();
+            let mut result = String::new();
+            let mut pos = 0;
+            for r in ranges {
+                result.push_str(&text[pos..r.start]);
+                result.push_str(&style.apply_to(&text[r.clone()]).to_string());
+                pos = r.end;
+            }
+            result.push_str(&text[pos..]);
+            result
+        })
+        .collect::<Vec<_>>()
+        .join(" - ")
+}
+
+fn make_rng(seed: Option<u64>) -> ChaCha8Rng {
+    match seed {
+        Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+        None => rand::make_rng(),
+    }
+}
+
+/// Calculates seed from the date and the file name.
+///
+/// FNV-1a hash is used here, because unlike the standard library hashers its
+/// result doesn't depend on the platform or the compiler version.
+fn daily_seed(date: NaiveDate, name: &str) -> u64 {
+    format!("{}:{}", date.format("%Y-%m-%d"), name)
+        .bytes()
+        .fold(0xcbf29ce484222325, |hash, b| {
+            (hash ^ b as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
+fn get_random(
+    file: &dyn DBFile,
+    seed: Option<u64>,
+    options: &DrawOptions,
+    config: &Config,
+) -> anyhow::Result<()> {
+    draw(file, make_rng(seed), options, "Random item", config)
+}
+
+fn get_next(
+    file: &dyn DBFile,
+    order_by: Option<&str>,
+    max_effort: Option<u32>,
+    config: &Config,
+) -> anyhow::Result<()> {
+    let item = file.get_next(order_by, max_effort)?;
+    show_drawn(file, item, "Next item", config)
+}
+
+fn get_today(file: &dyn DBFile, name: &str, config: &Config) -> anyhow::Result<()> {
+    let seed = daily_seed(Local::now().date_naive(), name);
+    draw(
+        file,
+        make_rng(Some(seed)),
+        &DrawOptions::default(),
+        "Today's item",
+        config,
+    )
+}
+
+fn draw(
+    file: &dyn DBFile,
+    mut rng: ChaCha8Rng,
+    options: &DrawOptions,
+    title: &str,
+    config: &Config,
+) -> anyhow::Result<()> {
+    let item = file.get_random(&mut rng, options)?;
+    show_drawn(file, item, title, config)
+}
+
+fn show_drawn(
+    file: &dyn DBFile,
+    item: Option<DbItem>,
+    title: &str,
+    config: &Config,
+) -> anyhow::Result<()> {
+    if let Some(item) = item {
+        file.record_draw(item.id, Local::now().naive_local())?;
         let fields_str = item_fields_to_string(&item);
-        println!("Random item is {}: {}", item.id, fields_str);
-        mark_done(file, item);
+        println!("{title} is {}: {}", item.id, fields_str);
+        mark_done(file, item, config)
+    } else if file.list_undone()?.is_empty() {
+        Err(RednextError::NothingToDraw("All items are complete".to_string()).into())
     } else {
-        println!("All items are complete");
+        Err(RednextError::NothingToDraw(
+            "No items can be drawn: the remaining ones are blocked or don't fit".to_string(),
+        )
+        .into())
+    }
+}
+
+/// Returns index of the randomly chosen weight.
+fn pick_weighted(rng: &mut dyn Rng, weights: &[u32]) -> Option<usize> {
+    let total = weights.iter().map(|w| *w as u64).sum::<u64>();
+    if total == 0 {
+        return None;
     }
+    let mut point = rng.random_range(0..total);
+    weights.iter().position(|w| {
+        if point < *w as u64 {
+            true
+        } else {
+            point -= *w as u64;
+            false
+        }
+    })
 }
 
-fn mark_done(file: &dyn DBFile, item: DbItem) {
+fn next(
+    db: &impl DB,
+    names: Vec<String>,
+    all: bool,
+    weights: &[(String, u32)],
+    seed: Option<u64>,
+    config: &Config,
+) -> anyhow::Result<()> {
+    let names = if all {
+        db.list_files()?
+    } else if names.is_empty() {
+        match &config.default_file {
+            Some(name) => vec![name.clone()],
+            None => {
+                return Err(RednextError::InvalidInput(format!(
+                    "No files are given, and there is no default file in {CONFIG_FILE}"
+                ))
+                .into());
+            }
+        }
+    } else {
+        names
+    };
+    if let Some((name, _)) = weights.iter().find(|(n, _)| !names.contains(n)) {
+        return Err(RednextError::InvalidInput(format!(
+            "Weight is given for {name}, which is not among the drawn files"
+        ))
+        .into());
+    }
+    let mut files = Vec::new();
+    for name in names {
+        let weight = weights
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map_or(1, |(_, w)| *w);
+        match db.open(&name) {
+            Ok(file) => files.push((name, file, weight)),
+            // One broken file shouldn't prevent drawing from the others, as in `list`
+            Err(e) if all => eprintln!("Skipping {name}: {e:#}"),
+            Err(e) => return Err(e),
+        }
+    }
+
+    let mut rng = make_rng(seed);
+    // Files without available items are excluded until we find something
+    while let Some(idx) = pick_weighted(
+        &mut rng,
+        &files.iter().map(|(_, _, w)| *w).collect::<Vec<_>>(),
+    ) {
+        let (name, file, _) = &files[idx];
+        // Each file is drawn from as `items <name> get-random` would do
+        let file_config = config.file(name);
+        let (drawn, title) = match file_config.strategy.unwrap_or(Strategy::Random) {
+            Strategy::Random => (
+                file.get_random(
+                    &mut rng,
+                    &DrawOptions::default().with_balance_by(file_config.balance_by.clone()),
+                ),
+                "Random item",
+            ),
+            Strategy::Sequential => (
+                file.get_next(file_config.order_by.as_deref(), None),
+                "Next item",
+            ),
+        };
+        match drawn {
+            Ok(Some(item)) => {
+                return show_drawn(
+                    file.as_ref(),
+                    Some(item),
+                    &format!("{title} from {name}"),
+                    config,
+                );
+            }
+            Ok(None) => {}
+            Err(e) if all => eprintln!("Skipping {name}: {e:#}"),
+            Err(e) => return Err(e),
+        }
+        files.remove(idx);
+    }
+    Err(RednextError::NothingToDraw("No items can be drawn".to_string()).into())
+}
+
+fn show_item(file: &dyn DBFile, id: u32, config: &Config) -> anyhow::Result<()> {
+    let item = file.get(id)?.ok_or(RednextError::ItemNotFound(id))?;
+    let format_time = |dt: NaiveDateTime| config.format_time(dt);
+
+    println!("{}", Style::new().bold().apply_to(format!("Item {id}")));
+    let mut table = config.table();
+    table.set_header(["Field", "Value", "Type"]);
+    for (field, desc) in item.fields.iter().zip(file.schema().fields.iter()) {
+        table.add_row([
+            field.name.clone(),
+            field.value.to_string(),
+            desc.field_type.to_string(),
+        ]);
+    }
+    println!("{table}");
+
+    let created = item.created_at.map_or("unknown".to_string(), format_time);
+    let done = item.completed_at.map_or("no".to_string(), format_time);
+    println!("Created:    {created}");
+    println!("Done:       {done}");
+    if item.pinned {
+        println!("Pinned:     yes");
+    }
+    let dependencies = file.dependencies(id)?;
+    if !dependencies.is_empty() {
+        let ids = dependencies
+            .iter()
+            .map(|d| d.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let blocked = if item.blocked { " (blocked)" } else { "" };
+        println!("Depends on: {ids}{blocked}");
+    }
+    let draws = file.draws(id)?;
+    if draws.is_empty() {
+        println!("Never drawn");
+    } else {
+        println!("Drawn {} times:", draws.len());
+        for time in draws {
+            println!("  {}", format_time(time));
+        }
+    }
+    Ok(())
+}
+
+fn set_done(
+    file: &dyn DBFile,
+    id: u32,
+    at: Option<NaiveDateTime>,
+    config: &Config,
+) -> anyhow::Result<()> {
+    let time = done_time(at, Local::now().naive_local())?;
+    let item = file.get(id)?.ok_or(RednextError::ItemNotFound(id))?;
+    file.done(id, time)?;
+    let new_time = config.format_time(time);
+    match item.completed_at {
+        Some(prev) => println!(
+            "Item {id} completion time is changed from {} to {new_time}",
+            config.format_time(prev)
+        ),
+        None => println!("Item {id} is done at {new_time}"),
+    }
+    Ok(())
+}
+
+/// Completion time given by the user, or now. Items can only be done in the past.
+fn done_time(at: Option<NaiveDateTime>, now: NaiveDateTime) -> anyhow::Result<NaiveDateTime> {
+    match at {
+        Some(at) if at > now => {
+            Err(RednextError::InvalidInput(format!("Completion time {at} is in the future")).into())
+        }
+        Some(at) => Ok(at),
+        None => Ok(now),
+    }
+}
+
+fn set_undone(file: &dyn DBFile, id: u32) -> anyhow::Result<()> {
+    let item = file.get(id)?.ok_or(RednextError::ItemNotFound(id))?;
+    if item.completed_at.is_none() {
+        println!("Item {id} is not done");
+    } else {
+        file.undone(id)?;
+        println!("Item {id} is marked as not done");
+    }
+    Ok(())
+}
+
+fn mark_done(file: &dyn DBFile, item: DbItem, config: &Config) -> anyhow::Result<()> {
     let done = Confirm::new()
         .with_prompt("Mark as done?")
-        .default(true)
-        .interact()
-        .unwrap();
+        .default(config.confirm_done)
+        .interact()?;
     if done {
-        file.done(item.id, Local::now().naive_local()).unwrap();
+        file.complete(item.id, Local::now().naive_local())?;
     }
+    Ok(())
 }
 
-fn find_by_name(file: &dyn DBFile, name: &str) {
-    let items = file.find(name).unwrap();
-    if !items.is_empty() {
-        for item in items {
-            let fields_str = item_fields_to_string(&item);
-            println!("{}. {}", item.id, fields_str);
+fn find_by_name(
+    file: &dyn DBFile,
+    name: Option<&str>,
+    field_args: Vec<FieldArg>,
+) -> anyhow::Result<()> {
+    let (fields, filters) = split_field_args(field_args);
+    let mut out = io::stdout().lock();
+    let mut found_any = false;
+    file.for_each_found(name.unwrap_or_default(), &fields, &filters, &mut |found| {
+        found_any = true;
+        writeln!(out, "{}. {}", found.item.id, highlight_matches(&found))?;
+        Ok(())
+    })?;
+    if !found_any {
+        println!("No matching items found");
+    }
+    Ok(())
+}
+
+fn find_everywhere(
+    db: &impl DB,
+    text: Option<&str>,
+    field_args: Vec<FieldArg>,
+) -> anyhow::Result<()> {
+    let (fields, filters) = split_field_args(field_args);
+    let bold = Style::new().bold();
+    let mut out = io::stdout().lock();
+    let mut found_files = 0;
+    for name in db.list_files()? {
+        // One broken file shouldn't stop the search in the others
+        let file = match db.open(&name) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("Skipping {name}: {e:#}");
+                continue;
+            }
+        };
+        // Files without searched or filtered fields cannot have matching items
+        let schema = file.schema();
+        let has_field = |name: &str, text: bool| {
+            schema
+                .fields
+                .iter()
+                .any(|d| d.name == name && (!text || d.field_type == DbFieldType::Text))
+        };
+        if !fields.iter().all(|f| has_field(f, true))
+            || !filters.iter().all(|f| has_field(&f.field, false))
+        {
+            continue;
         }
-    } else {
+        let mut file_found = false;
+        let searched =
+            file.for_each_found(text.unwrap_or_default(), &fields, &filters, &mut |found| {
+                if !file_found {
+                    if found_files > 0 {
+                        writeln!(out)?;
+                    }
+                    writeln!(out, "{}", bold.apply_to(&name))?;
+                    file_found = true;
+                    found_files += 1;
+                }
+                writeln!(out, "  {}. {}", found.item.id, highlight_matches(&found))?;
+                Ok(())
+            });
+        match searched {
+            Ok(()) => {}
+            // Output errors and invalid filters would be the same for every file
+            Err(e)
+                if e.chain().any(|c| c.is::<io::Error>())
+                    || matches!(
+                        e.downcast_ref::<RednextError>(),
+                        Some(RednextError::InvalidInput(_))
+                    ) =>
+            {
+                return Err(e);
+            }
+            Err(e) => eprintln!("Skipping {name}: {e:#}"),
+        }
+    }
+    if found_files == 0 {
         println!("No matching items found");
     }
+    Ok(())
 }
 
-fn enter_schema() -> DbSchema {
+fn enter_schema() -> anyhow::Result<DbSchema> {
     let mut fields = Vec::new();
     loop {
         let name_prompt = if fields.is_empty() {
@@ -370,8 +1599,19 @@ fn enter_schema() -> DbSchema {
         let field_name: String = Input::new()
             .with_prompt(name_prompt)
             .allow_empty(!fields.is_empty())
-            .interact_text()
-            .unwrap();
+            .validate_with(|name: &String| {
+                if name.is_empty() {
+                    Ok(())
+                } else if fields
+                    .iter()
+                    .any(|f: &DbFieldDesc| f.name.eq_ignore_ascii_case(name))
+                {
+                    Err(format!("Field {name} already exists"))
+                } else {
+                    validate_field_name(name).map_err(|e| e.to_string())
+                }
+            })
+            .interact_text()?;
         if field_name.is_empty() {
             break;
         }
@@ -379,8 +1619,7 @@ fn enter_schema() -> DbSchema {
             .with_prompt("Choose field type")
             .items(["Text", "Number", "Boolean", "Timestamp"])
             .default(0)
-            .interact()
-            .unwrap();
+            .interact()?;
         fields.push(DbFieldDesc::new(
             &field_name,
             match field_type {
@@ -392,7 +1631,23 @@ fn enter_schema() -> DbSchema {
             },
         ));
     }
-    DbSchema { fields }
+    let number_fields = fields
+        .iter()
+        .filter(|f| f.field_type == DbFieldType::Number)
+        .map(|f| f.name.clone())
+        .collect::<Vec<_>>();
+    let effort_field = if number_fields.is_empty() {
+        None
+    } else {
+        let choice = Select::new()
+            .with_prompt("Choose field with effort estimate in minutes")
+            .item("None")
+            .items(&number_fields)
+            .default(0)
+            .interact()?;
+        choice.checked_sub(1).map(|i| number_fields[i].clone())
+    };
+    Ok(DbSchema::new(fields).with_effort_field(effort_field))
 }
 
 fn new_file(
@@ -402,7 +1657,7 @@ fn new_file(
     delimiter: Option<u8>,
     no_header: bool,
 ) -> anyhow::Result<()> {
-    let schema = enter_schema();
+    let schema = enter_schema()?;
     let file = db
         .create(name, schema)
         .context("Failed to create a new file")?;
@@ -425,56 +1680,77 @@ fn import_csv(
         .has_headers(!no_header)
         .trim(Trim::All)
         .from_path(file_name)
-        .unwrap();
+        .with_context(|| format!("Cannot read {file_name:?}"))?;
     for r in reader.records() {
-        let record = r.context("Incorrect CSV record")?;
+        let record = r.context(RednextError::InvalidInput(
+            "Incorrect CSV record".to_string(),
+        ))?;
         let mut fields = Vec::new();
         for (i, field_desc) in file.schema().fields.iter().enumerate() {
-            let str = record.get(i).context("Not enough fields in CSV record")?;
+            let str = record.get(i).ok_or_else(|| {
+                RednextError::InvalidInput("Not enough fields in CSV record".to_string())
+            })?;
             let value = match field_desc.field_type {
                 DbFieldType::Text => DbValue::Text(str.to_string()),
                 DbFieldType::Number => {
-                    let num: i32 = str
-                        .parse()
-                        .context(format!("Failed to parse number '{str}'"))?;
+                    let num: i32 = str.parse().with_context(|| {
+                        RednextError::InvalidInput(format!("Failed to parse number '{str}'"))
+                    })?;
                     DbValue::Number(num)
                 }
                 DbFieldType::Boolean => {
-                    let b: bool = str.parse().context("Failed to parse boolean")?;
+                    let b: bool = str.parse().context(RednextError::InvalidInput(
+                        "Failed to parse boolean".to_string(),
+                    ))?;
                     DbValue::Boolean(b)
                 }
                 DbFieldType::DateTime => {
-                    let date: Date = str.parse().context("Failed to parse date")?;
+                    let date: Date = str.parse().context(RednextError::InvalidInput(
+                        "Failed to parse date".to_string(),
+                    ))?;
                     DbValue::DateTime(date.0)
                 }
+                _ => return Err(unsupported_type(&field_desc.field_type)),
             };
             fields.push(DbField {
                 name: field_desc.name.clone(),
                 value,
             });
         }
-        file.insert(&fields).unwrap();
+        file.insert(&fields, Local::now().naive_local())?;
     }
 
     Ok(())
 }
 
-fn delete(db: &impl DB, name: &str) {
+fn delete(db: &impl DB, name: &str) -> anyhow::Result<()> {
     let confirmation = Confirm::new()
         .with_prompt(format!("Are you sure you want to delete file {name}?"))
-        .interact()
-        .unwrap();
+        .interact()?;
 
     if confirmation {
-        db.delete(name).unwrap();
+        db.delete(name)?;
     }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
+    use std::fs;
+
     use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
 
-    use crate::Date;
+    use rand::{SeedableRng, rngs::ChaCha8Rng};
+    use rednext::{
+        db::{DB, DbField, DbFieldDesc, DbFieldType, DbItem, DbSchema, DbValue},
+        error::RednextError,
+        sqlite::SqliteDB,
+    };
+
+    use crate::{
+        Column, Date, config::Config, daily_seed, done_time, effort_parser, json_value,
+        list_columns, pick_weighted, set_done, set_undone, show_item,
+    };
 
     #[test]
     fn test_parse_date() {
@@ -499,4 +1775,151 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn test_done_time() {
+        let now: Date = "2026-03-10 12:00:00".parse().unwrap();
+        let past: Date = "2026-03-01".parse().unwrap();
+        let future: Date = "2026-03-11".parse().unwrap();
+        assert_eq!(done_time(None, now.0).unwrap(), now.0);
+        assert_eq!(done_time(Some(past.0), now.0).unwrap(), past.0);
+        assert!(done_time(Some(future.0), now.0).is_err());
+    }
+
+    #[test]
+    fn test_daily_seed() {
+        let date = NaiveDate::from_ymd_opt(2026, 1, 11).unwrap();
+        assert_eq!(daily_seed(date, "books"), daily_seed(date, "books"));
+        assert_ne!(daily_seed(date, "books"), daily_seed(date, "movies"));
+        assert_ne!(
+            daily_seed(date, "books"),
+            daily_seed(date.succ_opt().unwrap(), "books")
+        );
+    }
+
+    #[test]
+    fn test_parse_effort() {
+        assert_eq!(effort_parser("45"), Ok(45));
+        assert_eq!(effort_parser("30m"), Ok(30));
+        assert_eq!(effort_parser("2h"), Ok(120));
+        assert_eq!(effort_parser("1h30m"), Ok(90));
+        assert!(effort_parser("").is_err());
+        assert!(effort_parser("1d").is_err());
+        assert!(effort_parser("h30").is_err());
+        assert!(effort_parser("99999999h").is_err());
+        assert!(effort_parser("71582788h59m").is_err());
+    }
+
+    #[test]
+    fn test_list_columns() {
+        let schema = DbSchema::new(vec![
+            DbFieldDesc::new("title", DbFieldType::Text),
+            DbFieldDesc::new("pages", DbFieldType::Number),
+        ]);
+        let names = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            list_columns(&schema, &[], &[]).unwrap(),
+            vec![Column::Id, Column::Field(0), Column::Field(1), Column::Done]
+        );
+        assert_eq!(
+            list_columns(&schema, &names(&["pages", "ID"]), &[]).unwrap(),
+            vec![Column::Field(1), Column::Id]
+        );
+        assert_eq!(
+            list_columns(&schema, &[], &names(&["id", "done"])).unwrap(),
+            vec![Column::Field(0), Column::Field(1)]
+        );
+        assert!(list_columns(&schema, &names(&["author"]), &[]).is_err());
+    }
+
+    #[test]
+    fn test_pick_weighted() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        assert_eq!(pick_weighted(&mut rng, &[]), None);
+        assert_eq!(pick_weighted(&mut rng, &[0, 0]), None);
+        for _ in 0..10 {
+            assert_eq!(pick_weighted(&mut rng, &[0, 3, 0]), Some(1));
+        }
+        let first = (0..100)
+            .filter(|_| pick_weighted(&mut rng, &[9, 1]) == Some(0))
+            .count();
+        assert!(first > 70);
+    }
+
+    #[test]
+    fn test_json_value() {
+        let time = NaiveDate::from_ymd_opt(2026, 3, 1)
+            .unwrap()
+            .and_hms_opt(10, 0, 0)
+            .unwrap();
+        let field = |value| DbField {
+            name: "f".to_string(),
+            value,
+        };
+        let item = DbItem::new(
+            3,
+            vec![
+                field(DbValue::Text("Dune".to_string())),
+                field(DbValue::Number(412)),
+                field(DbValue::Boolean(true)),
+                field(DbValue::DateTime(time)),
+            ],
+        );
+        let values = [
+            Column::Id,
+            Column::Field(0),
+            Column::Field(1),
+            Column::Field(2),
+            Column::Field(3),
+            Column::Done,
+        ]
+        .map(|c| json_value(&item, c).to_string());
+        assert_eq!(
+            values,
+            [
+                "3",
+                "\"Dune\"",
+                "412",
+                "true",
+                "\"2026-03-01T10:00:00\"",
+                "null"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_item_commands() {
+        let dir = std::env::temp_dir().join(format!("rednext-test-cmd-{}", std::process::id()));
+        let db = SqliteDB::new(&dir);
+        let schema = DbSchema::new(vec![DbFieldDesc::new("title", DbFieldType::Text)]);
+        let file = db.create("books", schema).unwrap();
+        let time = NaiveDate::from_ymd_opt(2026, 3, 1)
+            .unwrap()
+            .and_hms_opt(10, 0, 0)
+            .unwrap();
+        let title = DbField {
+            name: "title".to_string(),
+            value: DbValue::Text("Dune".to_string()),
+        };
+        file.insert(&[title], time).unwrap();
+        let config = Config::default();
+
+        // Undone item stays as it is
+        set_undone(file.as_ref(), 1).unwrap();
+        assert!(file.get(1).unwrap().unwrap().completed_at.is_none());
+
+        let backdated = time + chrono::Days::new(1);
+        set_done(file.as_ref(), 1, Some(backdated), &config).unwrap();
+        assert_eq!(file.get(1).unwrap().unwrap().completed_at, Some(backdated));
+        set_undone(file.as_ref(), 1).unwrap();
+        assert!(file.get(1).unwrap().unwrap().completed_at.is_none());
+
+        let err = show_item(file.as_ref(), 2, &config).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<RednextError>(),
+            Some(RednextError::ItemNotFound(2))
+        ));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
@@ -0,0 +1,229 @@
+use std::{
+    io::{self, Write},
+    iter,
+};
+
+use console::{Style, measure_text_width};
+
+// Positions of the characters in comfy-table presets
+const LEFT_BORDER: usize = 0;
+const RIGHT_BORDER: usize = 1;
+const TOP_BORDER: usize = 2;
+const BOTTOM_BORDER: usize = 3;
+const LEFT_HEADER_INTERSECTION: usize = 4;
+const HEADER_LINES: usize = 5;
+const MIDDLE_HEADER_INTERSECTIONS: usize = 6;
+const RIGHT_HEADER_INTERSECTION: usize = 7;
+const VERTICAL_LINES: usize = 8;
+const HORIZONTAL_LINES: usize = 9;
+const MIDDLE_INTERSECTIONS: usize = 10;
+const LEFT_BORDER_INTERSECTIONS: usize = 11;
+const RIGHT_BORDER_INTERSECTIONS: usize = 12;
+const TOP_BORDER_INTERSECTIONS: usize = 13;
+const BOTTOM_BORDER_INTERSECTIONS: usize = 14;
+const TOP_LEFT_CORNER: usize = 15;
+const TOP_RIGHT_CORNER: usize = 16;
+const BOTTOM_LEFT_CORNER: usize = 17;
+const BOTTOM_RIGHT_CORNER: usize = 18;
+
+/// Table which is written row by row, looking the same as comfy-table with the same preset.
+/// Column widths must be known in advance, so the rows are usually measured in a separate pass.
+pub struct StreamTable<W: Write> {
+    out: W,
+    preset: Vec<char>,
+    widths: Vec<usize>,
+    rows: usize,
+}
+
+impl<W: Write> StreamTable<W> {
+    /// Writes the header and returns the table ready for the rows.
+    pub fn start(out: W, preset: &str, header: &[String], widths: &[usize]) -> io::Result<Self> {
+        let widths = header
+            .iter()
+            .zip(widths)
+            .map(|(h, w)| text_width(h).max(*w))
+            .collect();
+        let mut table = StreamTable {
+            out,
+            preset: preset.chars().collect(),
+            widths,
+            rows: 0,
+        };
+        table.border(
+            TOP_LEFT_CORNER,
+            TOP_BORDER,
+            TOP_BORDER_INTERSECTIONS,
+            TOP_RIGHT_CORNER,
+        )?;
+        table.content(header, None)?;
+        table.border(
+            LEFT_HEADER_INTERSECTION,
+            HEADER_LINES,
+            MIDDLE_HEADER_INTERSECTIONS,
+            RIGHT_HEADER_INTERSECTION,
+        )?;
+        Ok(table)
+    }
+
+    pub fn row(&mut self, cells: &[String], style: Option<&Style>) -> io::Result<()> {
+        if self.rows > 0 {
+            self.border(
+                LEFT_BORDER_INTERSECTIONS,
+                HORIZONTAL_LINES,
+                MIDDLE_INTERSECTIONS,
+                RIGHT_BORDER_INTERSECTIONS,
+            )?;
+        }
+        self.rows += 1;
+        self.content(cells, style)
+    }
+
+    /// Writes the bottom border and returns the output.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.border(
+            BOTTOM_LEFT_CORNER,
+            BOTTOM_BORDER,
+            BOTTOM_BORDER_INTERSECTIONS,
+            BOTTOM_RIGHT_CORNER,
+        )?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn visible(&self, chars: &[usize]) -> bool {
+        chars.iter().any(|c| self.preset[*c] != ' ')
+    }
+
+    fn line(
+        &self,
+        left: usize,
+        cells: impl Iterator<Item = String>,
+        middle: usize,
+        right: usize,
+    ) -> String {
+        let mut line = String::new();
+        if self.visible(&[
+            LEFT_BORDER,
+            LEFT_HEADER_INTERSECTION,
+            LEFT_BORDER_INTERSECTIONS,
+            TOP_LEFT_CORNER,
+            BOTTOM_LEFT_CORNER,
+        ]) {
+            line.push(self.preset[left]);
+        }
+        let vertical = self.visible(&[
+            VERTICAL_LINES,
+            MIDDLE_HEADER_INTERSECTIONS,
+            MIDDLE_INTERSECTIONS,
+            TOP_BORDER_INTERSECTIONS,
+            BOTTOM_BORDER_INTERSECTIONS,
+        ]);
+        for (i, cell) in cells.enumerate() {
+            if i > 0 && vertical {
+                line.push(self.preset[middle]);
+            }
+            line.push_str(&cell);
+        }
+        if self.visible(&[
+            RIGHT_BORDER,
+            RIGHT_HEADER_INTERSECTION,
+            RIGHT_BORDER_INTERSECTIONS,
+            TOP_RIGHT_CORNER,
+            BOTTOM_RIGHT_CORNER,
+        ]) {
+            line.push(self.preset[right]);
+        }
+        line
+    }
+
+    /// Writes horizontal line, unless all its characters are blank.
+    fn border(&mut self, left: usize, fill: usize, middle: usize, right: usize) -> io::Result<()> {
+        if !self.visible(&[left, fill, middle, right]) {
+            return Ok(());
+        }
+        let cells = self
+            .widths
+            .iter()
+            .map(|w| iter::repeat_n(self.preset[fill], w + 2).collect());
+        let line = self.line(left, cells, middle, right);
+        writeln!(self.out, "{line}")
+    }
+
+    fn content(&mut self, cells: &[String], style: Option<&Style>) -> io::Result<()> {
+        let lines = cells
+            .iter()
+            .map(|c| c.lines().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let height = lines.iter().map(Vec::len).max().unwrap_or(0).max(1);
+        for n in 0..height {
+            let cells = lines.iter().zip(&self.widths).map(|(cell, width)| {
+                let text = cell.get(n).copied().unwrap_or_default();
+                // Cells wider than measured break the borders, but don't fail the output
+                let padding = " ".repeat(width.saturating_sub(text_width(text)) + 1);
+                match style {
+                    Some(style) => format!(" {}{padding}", style.apply_to(text)),
+                    None => format!(" {text}{padding}"),
+                }
+            });
+            let line = self.line(LEFT_BORDER, cells, VERTICAL_LINES, RIGHT_BORDER);
+            writeln!(self.out, "{line}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Width of the widest line of the text in the terminal.
+pub fn text_width(text: &str) -> usize {
+    text.lines().map(measure_text_width).max().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        config::TableStyle,
+        output::{StreamTable, text_width},
+    };
+
+    #[test]
+    fn test_stream_table() {
+        let header = ["ID", "title", "Done"].map(String::from);
+        let rows = [
+            ["1", "The Hobbit", "2026-03-01 10:00"],
+            ["2", "Dune\nMessiah", ""],
+            ["10", "Солярис", ""],
+        ]
+        .map(|r| r.map(String::from));
+        let mut widths = vec![0; header.len()];
+        for row in rows.iter() {
+            for (w, cell) in widths.iter_mut().zip(row) {
+                *w = (*w).max(text_width(cell));
+            }
+        }
+
+        for style in [
+            TableStyle::Default,
+            TableStyle::Ascii,
+            TableStyle::Markdown,
+            TableStyle::Full,
+            TableStyle::Plain,
+        ] {
+            let mut expected = comfy_table::Table::new();
+            expected.load_preset(style.preset());
+            expected.set_header(header.clone());
+            expected.add_rows(rows.clone());
+
+            let mut table = StreamTable::start(vec![], style.preset(), &header, &widths).unwrap();
+            for row in rows.iter() {
+                table.row(row, None).unwrap();
+            }
+            let out = String::from_utf8(table.finish().unwrap()).unwrap();
+            assert_eq!(out, format!("{expected}\n"), "{style:?}");
+        }
+
+        // The row has changed after it was measured
+        let mut table =
+            StreamTable::start(vec![], TableStyle::Ascii.preset(), &header, &[1, 1, 1]).unwrap();
+        table.row(&rows[0], None).unwrap();
+        assert!(table.finish().is_ok());
+    }
+}
@@ -0,0 +1,227 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+};
+
+use anyhow::Result;
+use chrono::Datelike;
+
+use rednext::{
+    db::{DbFieldType, DbItem, DbSchema, DbValue},
+    error::RednextError,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Aggregate {
+    Sum(String),
+    Avg(String),
+}
+
+impl Aggregate {
+    fn field(&self) -> &str {
+        match self {
+            Aggregate::Sum(f) | Aggregate::Avg(f) => f,
+        }
+    }
+}
+
+impl Display for Aggregate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Aggregate::Sum(field) => write!(f, "Sum of {field}"),
+            Aggregate::Avg(field) => write!(f, "Avg of {field}"),
+        }
+    }
+}
+
+/// Group value. Dates are grouped by month.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GroupKey {
+    Text(String),
+    Number(i32),
+    Boolean(bool),
+    Month(i32, u32),
+}
+
+impl Display for GroupKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GroupKey::Text(s) => write!(f, "{s}"),
+            GroupKey::Number(n) => write!(f, "{n}"),
+            GroupKey::Boolean(b) => write!(f, "{b}"),
+            GroupKey::Month(y, m) => write!(f, "{y}-{m:02}"),
+        }
+    }
+}
+
+impl From<&DbValue> for GroupKey {
+    fn from(value: &DbValue) -> Self {
+        match value {
+            DbValue::Text(s) => GroupKey::Text(s.clone()),
+            DbValue::Number(n) => GroupKey::Number(*n),
+            DbValue::Boolean(b) => GroupKey::Boolean(*b),
+            DbValue::DateTime(dt) => GroupKey::Month(dt.year(), dt.month()),
+            value => GroupKey::Text(value.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ReportRow {
+    pub group: GroupKey,
+    pub items: usize,
+    pub done: usize,
+    /// Aggregated values in the same order as requested aggregates
+    pub values: Vec<f64>,
+}
+
+impl ReportRow {
+    pub fn percent(&self) -> f64 {
+        (self.done as f64) / (self.items as f64) * 100.0
+    }
+}
+
+/// Groups items by the field value, calculating number of items, number of done items and the
+/// aggregates for each group.
+pub fn group_by(
+    schema: &DbSchema,
+    items: &[DbItem],
+    field: &str,
+    aggregates: &[Aggregate],
+) -> Result<Vec<ReportRow>> {
+    let group_idx = field_index(schema, field)?;
+    let aggregate_idx = aggregates
+        .iter()
+        .map(|a| {
+            let idx = field_index(schema, a.field())?;
+            if schema.fields[idx].field_type == DbFieldType::Number {
+                Ok(idx)
+            } else {
+                Err(RednextError::SchemaMismatch(format!(
+                    "Cannot aggregate non-numeric field {}",
+                    a.field()
+                ))
+                .into())
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut groups = BTreeMap::<GroupKey, ReportRow>::new();
+    for item in items {
+        let key = GroupKey::from(&item.fields[group_idx].value);
+        let row = groups.entry(key.clone()).or_insert_with(|| ReportRow {
+            group: key,
+            items: 0,
+            done: 0,
+            values: vec![0.0; aggregates.len()],
+        });
+        row.items += 1;
+        if item.completed_at.is_some() {
+            row.done += 1;
+        }
+        for (value, idx) in row.values.iter_mut().zip(aggregate_idx.iter()) {
+            if let DbValue::Number(n) = item.fields[*idx].value {
+                *value += n as f64;
+            }
+        }
+    }
+
+    let mut rows = groups.into_values().collect::<Vec<_>>();
+    for row in rows.iter_mut() {
+        for (value, aggregate) in row.values.iter_mut().zip(aggregates) {
+            if let Aggregate::Avg(_) = aggregate {
+                *value /= row.items as f64;
+            }
+        }
+    }
+    Ok(rows)
+}
+
+fn field_index(schema: &DbSchema, field: &str) -> Result<usize> {
+    schema
+        .fields
+        .iter()
+        .position(|f| f.name == field)
+        .ok_or_else(|| RednextError::SchemaMismatch(format!("Unknown field {field}")).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use rednext::db::{DbField, DbFieldDesc, DbFieldType, DbItem, DbSchema, DbValue};
+
+    use crate::report::{Aggregate, GroupKey, ReportRow, group_by};
+
+    fn schema() -> DbSchema {
+        DbSchema::new(vec![
+            DbFieldDesc::new("genre", DbFieldType::Text),
+            DbFieldDesc::new("pages", DbFieldType::Number),
+            DbFieldDesc::new("added", DbFieldType::DateTime),
+        ])
+    }
+
+    fn item(id: u32, genre: &str, pages: i32, month: u32, done: bool) -> DbItem {
+        let added = NaiveDate::from_ymd_opt(2026, month, 10)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        let fields = vec![
+            DbField {
+                name: "genre".to_string(),
+                value: DbValue::Text(genre.to_string()),
+            },
+            DbField {
+                name: "pages".to_string(),
+                value: DbValue::Number(pages),
+            },
+            DbField {
+                name: "added".to_string(),
+                value: DbValue::DateTime(added),
+            },
+        ];
+        DbItem::new(id, fields).with_completed_at(done.then_some(added))
+    }
+
+    #[test]
+    fn test_group_by() {
+        let items = vec![
+            item(1, "fantasy", 100, 1, true),
+            item(2, "scifi", 200, 1, false),
+            item(3, "fantasy", 300, 2, false),
+        ];
+        let aggregates = vec![
+            Aggregate::Sum("pages".to_string()),
+            Aggregate::Avg("pages".to_string()),
+        ];
+        let rows = group_by(&schema(), &items, "genre", &aggregates).unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                ReportRow {
+                    group: GroupKey::Text("fantasy".to_string()),
+                    items: 2,
+                    done: 1,
+                    values: vec![400.0, 200.0],
+                },
+                ReportRow {
+                    group: GroupKey::Text("scifi".to_string()),
+                    items: 1,
+                    done: 0,
+                    values: vec![200.0, 200.0],
+                },
+            ]
+        );
+        assert_eq!(rows[0].percent(), 50.0);
+
+        let rows = group_by(&schema(), &items, "added", &[]).unwrap();
+        assert_eq!(
+            rows.iter().map(|r| r.group.to_string()).collect::<Vec<_>>(),
+            vec!["2026-01", "2026-02"]
+        );
+
+        assert!(group_by(&schema(), &items, "author", &[]).is_err());
+        let text_sum = vec![Aggregate::Sum("genre".to_string())];
+        assert!(group_by(&schema(), &items, "genre", &text_sum).is_err());
+    }
+}
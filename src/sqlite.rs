@@ -1,26 +1,154 @@
 use std::{
+    collections::HashSet,
     ffi::OsStr,
-    fs,
+    fs, io,
+    ops::Range,
     path::{Path, PathBuf},
     result,
+    sync::Arc,
+    thread,
+    time::Duration,
 };
 
-use anyhow::{Context, Result, anyhow};
+use anyhow::{Context, Result};
+use chrono::{Days, NaiveDate, NaiveDateTime};
+use rand::{Rng, RngExt};
+use regex::Regex;
 use rusqlite::{
-    Connection, OptionalExtension, Params, Row, params,
+    Connection, ErrorCode, OpenFlags, OptionalExtension, Params, Row, Transaction,
+    TransactionBehavior,
+    functions::FunctionFlags,
+    params, params_from_iter,
     types::{FromSql, FromSqlError, FromSqlResult, Value, ValueRef},
 };
 
-use crate::db::{DB, DBFile, DbField, DbFieldDesc, DbFieldType, DbItem, DbSchema, DbValue};
+use crate::db::{
+    DB, DBFile, DbField, DbFieldDesc, DbFieldType, DbItem, DbSchema, DbValue, DrawOptions,
+    FileNotFound, FileSummary, Filter, FoundItem, ItemQuery, ItemStatus, Operator, SORT_CREATED,
+    SORT_DONE, SORT_ID, SortKey, sealed::Sealed,
+};
+use crate::error::RednextError;
+
+/// Database upgrades. Migration `n` moves the file from `user_version` `n` to `n + 1`.
+/// Internal columns are prefixed, so they can't clash with the fields of the files created before
+/// the names were reserved.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE deps (
+        item_id INTEGER NOT NULL,
+        depends_on INTEGER NOT NULL,
+        PRIMARY KEY (item_id, depends_on)
+      )",
+    "ALTER TABLE items ADD COLUMN _rn_position INTEGER;
+     UPDATE items SET _rn_position = id;
+     ALTER TABLE items ADD COLUMN _rn_pinned INTEGER;",
+    "CREATE TABLE draws (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        item_id INTEGER NOT NULL,
+        drawn_at TIMESTAMP NOT NULL
+      )",
+    "ALTER TABLE schema ADD COLUMN role TEXT",
+    "ALTER TABLE items ADD COLUMN _rn_created_at TIMESTAMP",
+    "CREATE INDEX items_undone ON items(id) WHERE done_at IS NULL;
+     CREATE INDEX items_pinned ON items(_rn_pinned) WHERE _rn_pinned IS NOT NULL;",
+];
+
+/// Checks whether the table has the column. Missing tables have no columns.
+fn has_column(conn: &Connection, table: &str, column: &str) -> rusqlite::Result<bool> {
+    conn.query_one(
+        "SELECT EXISTS (SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2)",
+        params![table, column],
+        |row| row.get(0),
+    )
+}
+
+/// Role of the field holding effort estimates in the schema table.
+const EFFORT_ROLE: &str = "effort";
+
+/// Time to wait for other processes holding a lock on the file, if not configured.
+pub const DEFAULT_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Attempts of an operation failing because the file is locked, and the delay before the first
+/// retry, which grows with each attempt.
+const BUSY_RETRIES: u32 = 5;
+const RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// Number of random ids tried by `get_random` before falling back to counting the items.
+const SAMPLE_ATTEMPTS: u32 = 16;
 
+/// SQL condition which is true if the item has unfinished prerequisites.
+const BLOCKED: &str = "EXISTS (
+        SELECT 1 FROM deps JOIN items AS p ON p.id = deps.depends_on
+        WHERE deps.item_id = items.id AND p.done_at IS NULL
+      )";
+
+type BoxError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// Characters marking matches in the search results. They never appear in normal text.
+const MATCH_START: char = '\u{2}';
+const MATCH_END: char = '\u{3}';
+
+#[derive(Debug)]
 pub struct SqliteDB {
     path: PathBuf,
+    busy_timeout: Duration,
 }
 
 impl SqliteDB {
     pub fn new(db_path: &Path) -> Self {
         SqliteDB {
             path: db_path.to_path_buf(),
+            busy_timeout: DEFAULT_BUSY_TIMEOUT,
+        }
+    }
+
+    /// Sets how long to wait for other processes writing to the same file before failing.
+    pub fn with_busy_timeout(mut self, timeout: Duration) -> Self {
+        self.busy_timeout = timeout;
+        self
+    }
+
+    /// Prepares the connection for concurrent use. In WAL mode readers don't block the writer,
+    /// and writers wait for each other up to the busy timeout.
+    fn configure(&self, conn: &Connection) -> rusqlite::Result<()> {
+        conn.busy_timeout(self.busy_timeout)?;
+        // The mode is kept in the file. Some file systems don't support WAL, and the mode stays
+        // unchanged then.
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+        Ok(())
+    }
+
+    /// Removes the DB file with its WAL files, which are left if a process using the file was
+    /// killed.
+    fn remove_files(path: &Path) -> io::Result<()> {
+        fs::remove_file(path)?;
+        for ext in ["db-wal", "db-shm"] {
+            let aux = path.with_extension(ext);
+            if aux.exists() {
+                fs::remove_file(aux)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns path of the file. Names with `.db` extension are paths themselves, other names are
+    /// files in the DB directory.
+    fn file_path(&self, name: &str) -> Result<PathBuf> {
+        let path = Path::new(name);
+        if path.extension() == Some(OsStr::new("db")) {
+            Ok(path.to_path_buf())
+        } else {
+            validate_file_name(name)?;
+            Ok(self.path.join(format!("{name}.db")))
+        }
+    }
+
+    /// Returns error with the similar file names if the file doesn't exist.
+    fn check_exists(&self, name: &str, path: &Path) -> Result<()> {
+        if path.is_file() {
+            Ok(())
+        } else {
+            let files = self.list_files().unwrap_or_default();
+            Err(RednextError::from(FileNotFound::new(name, &files)).into())
         }
     }
 
@@ -36,7 +164,72 @@ impl SqliteDB {
                 })
             })?
             .collect::<rusqlite::Result<Vec<_>>>()?;
-        Ok(DbSchema { fields })
+        // Files of the older versions may be read without upgrading them
+        let effort_field = if has_column(conn, "schema", "role")? {
+            conn.query_one(
+                "SELECT name FROM schema WHERE role=?1",
+                params![EFFORT_ROLE],
+                |row| row.get(0),
+            )
+            .optional()?
+        } else {
+            None
+        };
+        Ok(DbSchema {
+            fields,
+            effort_field,
+        })
+    }
+
+    /// Counts items and finds the last activity. Works for files of the older versions too,
+    /// which may lack creation and draw times.
+    fn read_summary(conn: &Connection) -> rusqlite::Result<FileSummary> {
+        let created = if has_column(conn, "items", "_rn_created_at")? {
+            "max(ifnull(datetime(_rn_created_at), ''))"
+        } else {
+            "''"
+        };
+        let drawn = if has_column(conn, "draws", "drawn_at")? {
+            "(SELECT ifnull(datetime(max(drawn_at)), '') FROM draws)"
+        } else {
+            "''"
+        };
+        // Timestamps are normalized, because field values and completion times have different formats
+        conn.query_one(
+            format!(
+                "SELECT count(*), count(done_at), max(
+                   {created},
+                   max(ifnull(datetime(done_at), '')),
+                   {drawn}
+                 )
+                 FROM items"
+            )
+            .as_str(),
+            [],
+            |row| {
+                let last: Option<String> = row.get(2)?;
+                Ok(FileSummary {
+                    items: row.get(0)?,
+                    done: row.get(1)?,
+                    last_activity: last
+                        .and_then(|l| NaiveDateTime::parse_from_str(&l, "%Y-%m-%d %H:%M:%S").ok()),
+                })
+            },
+        )
+    }
+
+    fn write_effort_field(conn: &Connection, field: Option<&str>) -> rusqlite::Result<()> {
+        conn.execute(
+            "UPDATE schema SET role=NULL WHERE role=?1",
+            params![EFFORT_ROLE],
+        )?;
+        if let Some(field) = field {
+            conn.execute(
+                "UPDATE schema SET role=?1 WHERE name=?2",
+                params![EFFORT_ROLE, field],
+            )?;
+        }
+        Ok(())
     }
 
     fn write_schema(conn: &mut Connection, schema: &DbSchema) -> rusqlite::Result<()> {
@@ -75,7 +268,7 @@ impl SqliteDB {
                     DbFieldType::Boolean => "BOOLEAN",
                     DbFieldType::DateTime => "TIMESTAMP",
                 };
-                format!("\"{}\" {}", f.name.as_str(), sql_type)
+                format!("{} {}", quote_ident(&f.name), sql_type)
             })
             .collect::<Vec<_>>()
             .join(", ");
@@ -89,6 +282,80 @@ impl SqliteDB {
         conn.execute(create_table_sql.as_str(), [])?;
         Ok(())
     }
+
+    /// Creates full-text index over the text fields, if it doesn't exist yet. The index is kept in
+    /// sync with the items table by triggers.
+    fn ensure_search_index(conn: &mut Connection, schema: &DbSchema) -> rusqlite::Result<()> {
+        let columns = text_columns(schema);
+        if columns.is_empty() {
+            return Ok(());
+        }
+        let exists = |conn: &Connection| -> rusqlite::Result<bool> {
+            conn.query_one(
+                "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE name = 'items_fts')",
+                [],
+                |row| row.get(0),
+            )
+        };
+        if exists(conn)? {
+            return Ok(());
+        }
+
+        let names = columns.join(", ");
+        let with_prefix = |prefix: &str| {
+            columns
+                .iter()
+                .map(|c| format!("{prefix}.{c}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let (new_values, old_values) = (with_prefix("new"), with_prefix("old"));
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        if exists(&tx)? {
+            return Ok(());
+        }
+        tx.execute_batch(
+            format!(
+                "CREATE VIRTUAL TABLE items_fts USING fts5(
+                   {names},
+                   content = 'items',
+                   content_rowid = 'id',
+                   tokenize = 'unicode61 remove_diacritics 2'
+                 );
+                 CREATE TRIGGER items_fts_insert AFTER INSERT ON items BEGIN
+                   INSERT INTO items_fts (rowid, {names}) VALUES (new.id, {new_values});
+                 END;
+                 CREATE TRIGGER items_fts_delete AFTER DELETE ON items BEGIN
+                   INSERT INTO items_fts (items_fts, rowid, {names})
+                   VALUES ('delete', old.id, {old_values});
+                 END;
+                 CREATE TRIGGER items_fts_update AFTER UPDATE OF {names} ON items BEGIN
+                   INSERT INTO items_fts (items_fts, rowid, {names})
+                   VALUES ('delete', old.id, {old_values});
+                   INSERT INTO items_fts (rowid, {names}) VALUES (new.id, {new_values});
+                 END;
+                 INSERT INTO items_fts (items_fts) VALUES ('rebuild');"
+            )
+            .as_str(),
+        )?;
+        tx.commit()
+    }
+
+    fn migrate(conn: &mut Connection) -> rusqlite::Result<()> {
+        let version: u32 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        let latest = MIGRATIONS.len() as u32;
+        if version < latest {
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            // Another process may have upgraded the file before the lock was taken
+            let version: u32 = tx.pragma_query_value(None, "user_version", |row| row.get(0))?;
+            for migration in &MIGRATIONS[version as usize..] {
+                tx.execute_batch(migration)?;
+            }
+            tx.pragma_update(None, "user_version", latest)?;
+            tx.commit()?;
+        }
+        Ok(())
+    }
 }
 
 struct SqliteFile {
@@ -96,6 +363,8 @@ struct SqliteFile {
     schema: DbSchema,
 }
 
+impl Sealed for SqliteDB {}
+
 impl DB for SqliteDB {
     fn list_files(&self) -> Result<Vec<String>> {
         if self.path.exists() {
@@ -113,7 +382,7 @@ impl DB for SqliteDB {
                     })
                     .context("Cannot read databases")
             } else {
-                Err(anyhow!("Invalid DB path {:?}", self.path))
+                Err(RednextError::Storage(format!("Invalid DB path {:?}", self.path)).into())
             }
         } else {
             Ok(vec![])
@@ -121,51 +390,117 @@ impl DB for SqliteDB {
     }
 
     fn create(&self, name: &str, schema: DbSchema) -> Result<Box<dyn DBFile>> {
-        if !self.path.exists() {
-            fs::create_dir_all(&self.path).context("Cannot create directory")?;
+        schema.validate()?;
+        let file_path = self.file_path(name)?;
+        let dir = file_path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        if !dir.exists() {
+            fs::create_dir_all(dir).context("Cannot create directory")?;
         }
 
-        if !self.path.is_dir() {
-            return Err(anyhow!("{:?} is not a directory", self.path));
+        if !dir.is_dir() {
+            return Err(RednextError::Storage(format!("{dir:?} is not a directory")).into());
         }
 
-        let file_path = self.path.join(format!("{}.db", name));
-
         if file_path.exists() {
-            return Err(anyhow!("Database file {:?} already exists", file_path));
+            return Err(RednextError::Conflict(format!(
+                "Database file {file_path:?} already exists"
+            ))
+            .into());
         }
 
-        let mut conn = Connection::open(&file_path).context("Cannot create DB file")?;
-        Self::write_schema(&mut conn, &schema).context("Cannot write schema")?;
-        Self::create_items_table(&mut conn, &schema).context("Cannot create items table")?;
-        Ok(Box::new(SqliteFile {
-            connection: conn,
-            schema,
-        }))
+        let conn = Connection::open(&file_path).context("Cannot create DB file")?;
+        let init = move |mut conn: Connection| -> Result<Box<dyn DBFile>> {
+            self.configure(&conn).context("Cannot configure DB")?;
+            Self::write_schema(&mut conn, &schema).context("Cannot write schema")?;
+            Self::create_items_table(&mut conn, &schema).context("Cannot create items table")?;
+            Self::migrate(&mut conn).context("Cannot upgrade DB")?;
+            Self::ensure_search_index(&mut conn, &schema).context("Cannot create search index")?;
+            Self::write_effort_field(&conn, schema.effort_field.as_deref())
+                .context("Cannot write schema")?;
+            Ok(Box::new(SqliteFile::new(conn, schema)?))
+        };
+        // Half-created file would be listed, but couldn't be opened
+        init(conn).inspect_err(|_| {
+            let _ = Self::remove_files(&file_path);
+        })
     }
 
     fn open(&self, name: &str) -> Result<Box<dyn DBFile>> {
-        let mut path = self.path.clone();
-        path.push(name);
-        path.set_extension("db");
-        let conn = Connection::open(path).context("Cannot open DB")?;
+        let path = self.file_path(name)?;
+        self.check_exists(name, &path)?;
+        // Without the create flag, so the file can't be created if removed in between
+        let mut conn = retry_busy(|| {
+            let conn = Connection::open_with_flags(
+                &path,
+                OpenFlags::SQLITE_OPEN_READ_WRITE
+                    | OpenFlags::SQLITE_OPEN_URI
+                    | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+            )?;
+            self.configure(&conn)?;
+            Ok(conn)
+        })
+        .context("Cannot open DB")?;
+        retry_busy(|| Self::migrate(&mut conn)).context("Cannot upgrade DB")?;
         let schema = Self::read_schema(&conn).context("Cannot read schema")?;
+        retry_busy(|| Self::ensure_search_index(&mut conn, &schema))
+            .context("Cannot create search index")?;
+
+        Ok(Box::new(SqliteFile::new(conn, schema)?))
+    }
 
-        Ok(Box::new(SqliteFile {
-            connection: conn,
-            schema,
-        }))
+    fn summary(&self, name: &str) -> Result<(DbSchema, FileSummary)> {
+        let path = self.file_path(name)?;
+        self.check_exists(name, &path)?;
+        let conn = Connection::open_with_flags(
+            &path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY
+                | OpenFlags::SQLITE_OPEN_URI
+                | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .context("Cannot open DB")?;
+        conn.busy_timeout(self.busy_timeout)
+            .context("Cannot configure DB")?;
+        let schema = Self::read_schema(&conn).context("Cannot read schema")?;
+        let summary = Self::read_summary(&conn).context("Query error")?;
+        Ok((schema, summary))
     }
 
     fn delete(&self, name: &str) -> Result<()> {
-        let mut path = self.path.clone();
-        path.push(name);
-        path.set_extension("db");
-        fs::remove_file(path).context("Cannot delete file")
+        let path = self.file_path(name)?;
+        self.check_exists(name, &path)?;
+        Self::remove_files(&path).context("Cannot delete file")
     }
 }
 
 impl SqliteFile {
+    fn new(connection: Connection, schema: DbSchema) -> Result<Self> {
+        Self::add_regexp_function(&connection).context("Cannot register SQL functions")?;
+        Ok(SqliteFile { connection, schema })
+    }
+
+    /// Adds `REGEXP` operator support. Compiled regular expressions are cached by SQLite for the
+    /// statement.
+    fn add_regexp_function(conn: &Connection) -> rusqlite::Result<()> {
+        conn.create_scalar_function(
+            "regexp",
+            2,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |ctx| {
+                let regex: Arc<Regex> = ctx.get_or_create_aux(0, |v| -> Result<_, BoxError> {
+                    Ok(Regex::new(v.as_str()?)?)
+                })?;
+                let text = ctx
+                    .get_raw(1)
+                    .as_str_or_null()
+                    .map_err(|e| rusqlite::Error::UserFunctionError(e.into()))?;
+                Ok(text.is_some_and(|t| regex.is_match(t)))
+            },
+        )
+    }
+
     fn to_db_item(&self, row: &Row) -> rusqlite::Result<DbItem> {
         let fields: rusqlite::Result<Vec<DbField>> = self
             .schema
@@ -187,56 +522,324 @@ impl SqliteFile {
         Ok(DbItem {
             id: row.get("id")?,
             fields: fields?,
+            created_at: row.get("_rn_created_at")?,
             completed_at: row.get("done_at")?,
+            blocked: row.get("_rn_blocked")?,
+            pinned: row.get::<_, Option<u32>>("_rn_pinned")?.is_some(),
         })
     }
 
-    fn select_items<P: Params>(
+    /// Selects items matching the filter, passing them to `f` as they are read.
+    fn for_each_selected<P: Params>(
         &self,
         filter: Option<&str>,
         params: P,
         order_by: Option<&str>,
-    ) -> Result<Vec<DbItem>> {
-        let ord = order_by.unwrap_or("id");
+        f: &mut dyn FnMut(DbItem) -> Result<()>,
+    ) -> Result<()> {
+        let ord = order_by.unwrap_or("_rn_position, id");
         let base_query = self.base_select();
         let mut q = filter
             .map(|c| format!("{base_query} WHERE {c}"))
             .unwrap_or(base_query);
         q.push_str(&format!(" ORDER BY {ord}"));
         let mut stmt = self.connection.prepare(&q)?;
-        let iter = stmt.query_map(params, |row| self.to_db_item(row))?;
-        iter.collect::<rusqlite::Result<Vec<_>>>()
-            .context("Item query error")
+        let mut rows = stmt.query(params)?;
+        while let Some(row) = rows.next().context("Item query error")? {
+            f(self.to_db_item(row).context("Item query error")?)?;
+        }
+        Ok(())
     }
 
     fn base_select(&self) -> String {
+        format!("SELECT {} FROM items", self.item_columns())
+    }
+
+    fn item_columns(&self) -> String {
         let fields = self
             .schema
             .fields
             .iter()
-            .map(|f| format!("\"{}\"", f.name.as_str()))
+            .map(|f| quote_ident(&f.name))
             .collect::<Vec<_>>()
             .join(", ");
-        format!("SELECT id, {fields}, done_at FROM items")
+        format!("id, {fields}, _rn_created_at, done_at, _rn_pinned, {BLOCKED} AS _rn_blocked")
+    }
+
+    fn check_field(&self, name: &str) -> Result<()> {
+        if self.schema.fields.iter().any(|f| f.name == name) {
+            Ok(())
+        } else {
+            Err(RednextError::SchemaMismatch(format!("Unknown field {name}")).into())
+        }
+    }
+
+    /// Converts filters into SQL condition with positional parameters, checking that the values
+    /// match the field types.
+    fn filter_conditions(&self, filters: &[Filter]) -> Result<(Vec<String>, Vec<Value>)> {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        for filter in filters {
+            let field = self
+                .schema
+                .fields
+                .iter()
+                .find(|f| f.name == filter.field)
+                .ok_or_else(|| {
+                    RednextError::SchemaMismatch(format!("Unknown field {}", filter.field))
+                })?;
+            let column = quote_ident(&field.name);
+            let op = filter.op;
+            let raw = filter.value.trim();
+            let unsupported = || {
+                RednextError::InvalidInput(format!(
+                    "Operator {op} cannot be used with {} field {}",
+                    field.field_type, field.name
+                ))
+            };
+            match (&field.field_type, op) {
+                (DbFieldType::Text, Operator::Matches) => {
+                    Regex::new(&filter.value).with_context(|| {
+                        RednextError::InvalidInput(format!(
+                            "Invalid regular expression in {filter}"
+                        ))
+                    })?;
+                    conditions.push(format!("{column} REGEXP ?"));
+                    values.push(filter.value.clone().into());
+                }
+                (DbFieldType::Text, Operator::Eq | Operator::Ne) => {
+                    conditions.push(format!("{column} {op} ?"));
+                    values.push(filter.value.clone().into());
+                }
+                (DbFieldType::Number, op) if op != Operator::Matches => {
+                    let n: i32 = raw.parse().with_context(|| {
+                        RednextError::InvalidInput(format!("Invalid number in {filter}"))
+                    })?;
+                    conditions.push(format!("{column} {op} ?"));
+                    values.push(n.into());
+                }
+                (DbFieldType::Boolean, Operator::Eq | Operator::Ne) => {
+                    let b: bool = raw.parse().with_context(|| {
+                        RednextError::InvalidInput(format!(
+                            "Invalid boolean in {filter}, expected true or false"
+                        ))
+                    })?;
+                    conditions.push(format!("{column} {op} ?"));
+                    values.push(b.into());
+                }
+                (DbFieldType::DateTime, op) if op != Operator::Matches => {
+                    let (from, to) = time_range(raw).ok_or_else(|| {
+                        RednextError::InvalidInput(format!(
+                            "Invalid date in {filter}, expected YYYY-MM-DD or YYYY-MM-DD HH:MM:SS"
+                        ))
+                    })?;
+                    let column = format!("datetime({column})");
+                    // Date without time means the whole day
+                    let (condition, bounds) = match op {
+                        Operator::Eq if from == to => (format!("{column} = ?"), vec![from]),
+                        Operator::Ne if from == to => (format!("{column} != ?"), vec![from]),
+                        Operator::Le if from == to => (format!("{column} <= ?"), vec![from]),
+                        Operator::Gt if from == to => (format!("{column} > ?"), vec![from]),
+                        Operator::Eq => {
+                            (format!("({column} >= ? AND {column} < ?)"), vec![from, to])
+                        }
+                        Operator::Ne => (
+                            format!("NOT ({column} >= ? AND {column} < ?)"),
+                            vec![from, to],
+                        ),
+                        Operator::Lt => (format!("{column} < ?"), vec![from]),
+                        Operator::Ge => (format!("{column} >= ?"), vec![from]),
+                        Operator::Le => (format!("{column} < ?"), vec![to]),
+                        Operator::Gt => (format!("{column} >= ?"), vec![to]),
+                        Operator::Matches => return Err(unsupported().into()),
+                    };
+                    conditions.push(condition);
+                    values.extend(
+                        bounds
+                            .iter()
+                            .map(|b| b.format("%Y-%m-%d %H:%M:%S").to_string().into()),
+                    );
+                }
+                _ => return Err(unsupported().into()),
+            }
+        }
+        Ok((conditions, values))
+    }
+
+    /// Returns SQL condition for the query status and filters.
+    fn query_condition(&self, query: &ItemQuery) -> Result<(String, Vec<Value>)> {
+        let (mut conditions, values) = self.filter_conditions(&query.filters)?;
+        match query.status {
+            ItemStatus::All => {}
+            ItemStatus::Done => conditions.push("done_at IS NOT NULL".to_string()),
+            ItemStatus::Undone => conditions.push("done_at IS NULL".to_string()),
+            ItemStatus::Pinned => conditions.push("_rn_pinned IS NOT NULL".to_string()),
+        }
+        Ok((conditions.join(" AND "), values))
+    }
+
+    /// Converts sort keys into SQL order. Items with equal keys are ordered by id.
+    fn sort_order(&self, sort: &[SortKey]) -> Result<String> {
+        let mut order = sort
+            .iter()
+            .map(|key| {
+                let column = if self.schema.fields.iter().any(|f| f.name == key.field) {
+                    quote_ident(&key.field)
+                } else {
+                    match key.field.as_str() {
+                        SORT_ID => "id".to_string(),
+                        SORT_CREATED => "_rn_created_at".to_string(),
+                        SORT_DONE => "done_at".to_string(),
+                        _ => {
+                            return Err(RednextError::SchemaMismatch(format!(
+                                "Unknown field {}",
+                                key.field
+                            ))
+                            .into());
+                        }
+                    }
+                };
+                let direction = if key.descending { "DESC" } else { "ASC" };
+                Ok(format!("{column} {direction}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        order.push("id".to_string());
+        Ok(order.join(", "))
+    }
+
+    /// Returns SQL condition for items which can be drawn.
+    fn available_filter(&self, options: &DrawOptions) -> Result<String> {
+        let mut filter = format!("done_at IS NULL AND NOT {BLOCKED}");
+        if let Some(max_effort) = options.max_effort {
+            let field = self.schema.effort_field.as_ref().ok_or_else(|| {
+                RednextError::SchemaMismatch("The file has no effort field".to_string())
+            })?;
+            filter.push_str(&format!(" AND {} <= {max_effort}", quote_ident(field)));
+        }
+        Ok(filter)
+    }
+
+    /// Narrows the filter of available items down by the balancing and cooldown rules. The rules
+    /// become conditions with parameters, whose values are returned along with the filter, so
+    /// the items are never loaded to apply them.
+    fn draw_filter(
+        &self,
+        rng: &mut dyn Rng,
+        mut filter: String,
+        options: &DrawOptions,
+    ) -> Result<(String, Vec<Value>)> {
+        let mut values = Vec::new();
+        for cooldown in options.cooldown.iter() {
+            self.check_field(&cooldown.field)?;
+            let recent = self
+                .recent_values(&cooldown.field, cooldown.draws)?
+                .into_iter()
+                .collect::<Vec<_>>();
+            if recent.is_empty() {
+                continue;
+            }
+            let column = quote_ident(&cooldown.field);
+            let condition = format!(" AND CAST({column} AS TEXT) IS NOT ?").repeat(recent.len());
+            let mut narrowed = values.clone();
+            narrowed.extend(recent.into_iter().map(Value::from));
+            let left: bool = self
+                .connection
+                .query_one(
+                    format!("SELECT EXISTS (SELECT 1 FROM items WHERE {filter}{condition})")
+                        .as_str(),
+                    params_from_iter(&narrowed),
+                    |row| row.get(0),
+                )
+                .context("Query error")?;
+            // Cooldown is a soft rule, it's better to repeat than to draw nothing.
+            if left {
+                filter.push_str(&condition);
+                values = narrowed;
+            }
+        }
+
+        if let Some(field) = &options.balance_by {
+            self.check_field(field)?;
+            let column = quote_ident(field);
+            let groups = self
+                .connection
+                .prepare(
+                    format!(
+                        "SELECT DISTINCT CAST({column} AS TEXT) FROM items WHERE {filter} ORDER BY 1"
+                    )
+                    .as_str(),
+                )?
+                .query_map(params_from_iter(&values), |row| {
+                    row.get::<_, Option<String>>(0)
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()
+                .context("Query error")?;
+            // Without groups there is nothing to draw, and the filter already says so
+            if !groups.is_empty() {
+                let group = groups[rng.random_range(0..groups.len())].clone();
+                filter.push_str(&format!(" AND CAST({column} AS TEXT) IS ?"));
+                values.push(group.into());
+            }
+        }
+        Ok((filter, values))
+    }
+
+    /// Returns values of the field in the last `draws` drawn items.
+    fn recent_values(&self, field: &str, draws: u32) -> Result<HashSet<Option<String>>> {
+        let mut stmt = self.connection.prepare(
+            format!(
+                "SELECT CAST(items.{} AS TEXT)
+                 FROM draws JOIN items ON items.id = draws.item_id
+                 ORDER BY draws.id DESC
+                 LIMIT ?1",
+                quote_ident(field)
+            )
+            .as_str(),
+        )?;
+        stmt.query_map(params![draws], |row| row.get(0))?
+            .collect::<rusqlite::Result<HashSet<_>>>()
+            .context("Query error")
+    }
+
+    /// Returns the first available pinned item.
+    fn first_pinned(&self, filter: &str) -> Result<Option<DbItem>> {
+        let base_query = self.base_select();
+        self.connection
+            .query_one(
+                format!(
+                    "{base_query}
+                     WHERE _rn_pinned IS NOT NULL AND {filter}
+                     ORDER BY _rn_pinned
+                     LIMIT 1"
+                )
+                .as_str(),
+                [],
+                |row| self.to_db_item(row),
+            )
+            .optional()
+            .context("Query error")
     }
 }
 
+impl Sealed for SqliteFile {}
+
 impl DBFile for SqliteFile {
     fn schema(&self) -> DbSchema {
         self.schema.clone()
     }
 
-    fn insert(&self, fields: &[DbField]) -> Result<()> {
+    fn insert(&self, fields: &[DbField], time: NaiveDateTime) -> Result<()> {
         let field_names = fields
             .iter()
-            .map(|f| format!("\"{}\"", f.name.as_str()))
+            .map(|f| quote_ident(&f.name))
             .collect::<Vec<_>>()
             .join(", ");
         let placeholders = (1..=fields.len())
             .map(|i| format!("?{}", i))
             .collect::<Vec<_>>()
             .join(", ");
-        let values: Vec<Value> = fields
+        let created_idx = fields.len() + 1;
+        let mut values: Vec<Value> = fields
             .iter()
             .map(|f| match &f.value {
                 DbValue::Text(s) => s.clone().into(),
@@ -245,9 +848,15 @@ impl DBFile for SqliteFile {
                 DbValue::DateTime(dt) => dt.format("%Y-%m-%dT%H:%M:%S").to_string().into(),
             })
             .collect::<Vec<_>>();
+        values.push(time.format("%Y-%m-%dT%H:%M:%S").to_string().into());
         self.connection
             .execute(
-                format!("INSERT INTO items ({field_names}) VALUES({placeholders})").as_str(),
+                format!(
+                    "INSERT INTO items ({field_names}, _rn_created_at, _rn_position)
+                     VALUES({placeholders}, ?{created_idx},
+                       (SELECT ifnull(max(_rn_position), 0) + 1 FROM items))"
+                )
+                .as_str(),
                 rusqlite::params_from_iter(values),
             )
             .context("Cannot insert item")?;
@@ -255,45 +864,262 @@ impl DBFile for SqliteFile {
     }
 
     fn delete(&self, id: u32) -> Result<()> {
-        self.connection
-            .execute("DELETE FROM items WHERE id=?1", params![id])
+        // The item and its dependencies are removed together, so no dangling ones are left
+        let tx = Transaction::new_unchecked(&self.connection, TransactionBehavior::Immediate)?;
+        tx.execute("DELETE FROM items WHERE id=?1", params![id])
             .context("Cannot delete item")?;
+        tx.execute(
+            "DELETE FROM deps WHERE item_id=?1 OR depends_on=?1",
+            params![id],
+        )
+        .context("Cannot delete item dependencies")?;
+        tx.commit()?;
         Ok(())
     }
 
-    fn list_items(&self) -> Result<Vec<DbItem>> {
-        self.select_items(None, [], None)
+    fn read_snapshot(&self, f: &mut dyn FnMut() -> Result<()>) -> Result<()> {
+        let tx = self.connection.unchecked_transaction()?;
+        f()?;
+        tx.commit()?;
+        Ok(())
     }
 
-    fn list_done(&self) -> Result<Vec<DbItem>> {
-        self.select_items(Some("done_at IS NOT NULL"), [], Some("done_at"))
+    fn for_each_item(
+        &self,
+        query: &ItemQuery,
+        f: &mut dyn FnMut(DbItem) -> Result<()>,
+    ) -> Result<()> {
+        let (filter, values) = self.query_condition(query)?;
+        let mut ord = if query.sort.is_empty() {
+            match query.status {
+                ItemStatus::Done => "done_at".to_string(),
+                ItemStatus::Pinned => "_rn_pinned".to_string(),
+                ItemStatus::All | ItemStatus::Undone => "_rn_position, id".to_string(),
+            }
+        } else {
+            self.sort_order(&query.sort)?
+        };
+        if query.limit.is_some() || query.offset > 0 {
+            // Negative limit means no limit
+            let limit = query.limit.map_or(-1, i64::from);
+            ord.push_str(&format!(" LIMIT {limit} OFFSET {}", query.offset));
+        }
+        self.for_each_selected(
+            (!filter.is_empty()).then_some(filter.as_str()),
+            params_from_iter(values),
+            Some(&ord),
+            f,
+        )
     }
 
-    fn list_undone(&self) -> Result<Vec<DbItem>> {
-        self.select_items(Some("done_at IS NULL"), [], None)
+    fn count(&self, query: &ItemQuery) -> Result<u32> {
+        let (filter, values) = self.query_condition(query)?;
+        let filter = if filter.is_empty() {
+            filter
+        } else {
+            format!(" WHERE {filter}")
+        };
+        self.connection
+            .query_one(
+                format!("SELECT count(*) FROM items{filter}").as_str(),
+                params_from_iter(values),
+                |row| row.get(0),
+            )
+            .context("Query error")
     }
 
-    fn find(&self, item_name: &str) -> Result<Vec<DbItem>> {
-        let pattern = format!("%{item_name}%");
-        let fields = self.schema.fields.iter().flat_map(|f| {
-            if f.field_type == DbFieldType::Text {
-                Some(format!("{} LIKE ?1", f.name))
-            } else {
-                None
+    fn for_each_found(
+        &self,
+        query: &str,
+        fields: &[String],
+        filters: &[Filter],
+        f: &mut dyn FnMut(FoundItem) -> Result<()>,
+    ) -> Result<()> {
+        for name in fields {
+            match self.schema.fields.iter().find(|f| f.name == *name) {
+                Some(f) if f.field_type == DbFieldType::Text => {}
+                Some(_) => {
+                    return Err(RednextError::SchemaMismatch(format!(
+                        "Field {name} is not a text field and cannot be searched"
+                    ))
+                    .into());
+                }
+                None => {
+                    return Err(
+                        RednextError::SchemaMismatch(format!("Unknown field {name}")).into(),
+                    );
+                }
             }
-        });
-        let filter = fields.collect::<Vec<_>>().join(" OR ");
-        self.select_items(Some(filter.as_str()), params![pattern], None)
+        }
+        let searched = self
+            .schema
+            .fields
+            .iter()
+            .filter(|f| {
+                f.field_type == DbFieldType::Text && (fields.is_empty() || fields.contains(&f.name))
+            })
+            .map(|f| quote_ident(&f.name))
+            .collect::<Vec<_>>();
+        let (conditions, mut values) = self.filter_conditions(filters)?;
+        let Some(terms) = fts_query(query) else {
+            if filters.is_empty() {
+                return Ok(());
+            }
+            return self.for_each_selected(
+                Some(conditions.join(" AND ").as_str()),
+                params_from_iter(values),
+                None,
+                &mut |item| {
+                    f(FoundItem {
+                        matches: vec![vec![]; item.fields.len()],
+                        item,
+                    })
+                },
+            );
+        };
+        // Nothing to search in, if the schema has no text fields at all
+        if searched.is_empty() {
+            return Ok(());
+        }
+
+        // Highlights are calculated for all indexed fields, in the order of the index columns
+        let highlights = (0..text_columns(&self.schema).len())
+            .map(|i| format!(", highlight(items_fts, {i}, char(2), char(3)) AS _rn_hl{i}"))
+            .collect::<String>();
+        let filter = conditions
+            .iter()
+            .map(|c| format!(" AND {c}"))
+            .collect::<String>();
+        let mut stmt = self.connection.prepare(
+            format!(
+                "SELECT {}, hits.* FROM items
+                 JOIN (
+                   SELECT rowid AS _rn_hit_id, rank AS _rn_rank{highlights} FROM items_fts WHERE items_fts MATCH ?1
+                 ) AS hits ON hits._rn_hit_id = items.id
+                 WHERE 1{filter}
+                 ORDER BY hits._rn_rank, items.id",
+                self.item_columns()
+            )
+            .as_str(),
+        )?;
+        values.insert(0, format!("{{{}}} : ({terms})", searched.join(" ")).into());
+        let to_found = |row: &Row| -> rusqlite::Result<FoundItem> {
+            let item = self.to_db_item(row)?;
+            let mut column = 0;
+            let mut matches = Vec::new();
+            for field in self.schema.fields.iter() {
+                if field.field_type == DbFieldType::Text {
+                    let highlighted: Option<String> =
+                        row.get(format!("_rn_hl{column}").as_str())?;
+                    matches.push(highlighted.map_or(vec![], |h| match_ranges(&h)));
+                    column += 1;
+                } else {
+                    matches.push(vec![]);
+                }
+            }
+            Ok(FoundItem { item, matches })
+        };
+        let mut rows = stmt.query(params_from_iter(values))?;
+        while let Some(row) = rows.next().context("Search error")? {
+            f(to_found(row).context("Search error")?)?;
+        }
+        Ok(())
+    }
+
+    fn get_random(&self, rng: &mut dyn Rng, options: &DrawOptions) -> Result<Option<DbItem>> {
+        let filter = self.available_filter(options)?;
+        if let Some(item) = self.first_pinned(&filter)? {
+            return Ok(Some(item));
+        }
+
+        let (filter, values) = self.draw_filter(rng, filter, options)?;
+
+        let (min, max): (Option<u32>, Option<u32>) = self
+            .connection
+            .query_one(
+                // Separate subqueries take one index lookup each, min and max together scan it
+                "SELECT (SELECT min(id) FROM items WHERE done_at IS NULL),
+                        (SELECT max(id) FROM items WHERE done_at IS NULL)",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .context("Query error")?;
+        let (Some(min), Some(max)) = (min, max) else {
+            return Ok(None);
+        };
+
+        // Probing random ids takes a few index lookups, and every available item has the same
+        // chance to be hit. Only when most of the ids are done, deleted or filtered out, the
+        // available items are counted. The ids are probed in the order given by the generator,
+        // so the same seed always picks the same item as long as the list is not modified.
+        let base_query = self.base_select();
+        let mut probe = self
+            .connection
+            .prepare(format!("{base_query} WHERE id = ? AND {filter}").as_str())?;
+        for _ in 0..SAMPLE_ATTEMPTS {
+            let id = rng.random_range(min..=max);
+            let probe_values = std::iter::once(Value::from(id)).chain(values.iter().cloned());
+            if let Some(item) = probe
+                .query_one(params_from_iter(probe_values), |row| self.to_db_item(row))
+                .optional()
+                .context("Query error")?
+            {
+                return Ok(Some(item));
+            }
+        }
+
+        let count: u32 = self
+            .connection
+            .query_one(
+                format!("SELECT count(*) FROM items WHERE {filter}").as_str(),
+                params_from_iter(&values),
+                |row| row.get(0),
+            )
+            .context("Query error")?;
+        if count == 0 {
+            return Ok(None);
+        }
+
+        let offset = rng.random_range(0..count);
+        self.connection
+            .query_one(
+                format!(
+                    "{base_query}
+                     WHERE {filter}
+                     ORDER BY id
+                     LIMIT 1 OFFSET ?"
+                )
+                .as_str(),
+                params_from_iter(values.into_iter().chain([Value::from(offset)])),
+                |row| self.to_db_item(row),
+            )
+            .optional()
+            .context("Query error")
     }
 
-    fn get_random(&self) -> Result<Option<DbItem>> {
+    fn get_next(&self, order_by: Option<&str>, max_effort: Option<u32>) -> Result<Option<DbItem>> {
+        let filter = self.available_filter(&DrawOptions {
+            max_effort,
+            ..Default::default()
+        })?;
+        if let Some(item) = self.first_pinned(&filter)? {
+            return Ok(Some(item));
+        }
+
+        let ord = match order_by {
+            Some(field) => {
+                self.check_field(field)?;
+                format!("{}, _rn_position, id", quote_ident(field))
+            }
+            None => "_rn_position, id".to_string(),
+        };
         let base_query = self.base_select();
         self.connection
             .query_one(
                 format!(
                     "{base_query}
-                     WHERE done_at IS NULL
-                     ORDER BY random()
+                     WHERE {filter}
+                     ORDER BY {ord}
                      LIMIT 1"
                 )
                 .as_str(),
@@ -316,18 +1142,35 @@ impl DBFile for SqliteFile {
             .context("Query error")
     }
 
-    fn done(&self, id: u32, time: chrono::NaiveDateTime) -> Result<()> {
+    fn done(&self, id: u32, time: NaiveDateTime) -> Result<()> {
         let count = self
             .connection
             .execute(
-                "UPDATE items SET done_at=?1 WHERE id =?2",
+                "UPDATE items SET done_at=?1, _rn_pinned=NULL WHERE id =?2",
                 params![time, id],
             )
             .context("Cannot update item ")?;
         if count == 1 {
             Ok(())
         } else {
-            Err(anyhow!("Expect exact one item, but got {}", count))
+            Err(RednextError::ItemNotFound(id).into())
+        }
+    }
+
+    fn complete(&self, id: u32, time: NaiveDateTime) -> Result<()> {
+        let count = retry_busy(|| {
+            self.connection.execute(
+                "UPDATE items SET done_at=?1, _rn_pinned=NULL WHERE id=?2 AND done_at IS NULL",
+                params![time, id],
+            )
+        })
+        .context("Cannot update item")?;
+        if count == 1 {
+            Ok(())
+        } else if self.get(id)?.is_some() {
+            Err(RednextError::Conflict(format!("Item {id} is already done")).into())
+        } else {
+            Err(RednextError::ItemNotFound(id).into())
         }
     }
 
@@ -340,38 +1183,336 @@ impl DBFile for SqliteFile {
         if count == 1 {
             Ok(())
         } else {
-            Err(anyhow!("Item with id {id} is not found"))
+            Err(RednextError::ItemNotFound(id).into())
         }
     }
-}
 
-impl FromSql for DbFieldType {
-    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
-        value
-            .as_str()?
-            .parse()
-            .map_err(|e| FromSqlError::Other(Box::new(e)))
-    }
-}
+    fn add_dependency(&self, id: u32, depends_on: u32) -> Result<()> {
+        if id == depends_on {
+            return Err(
+                RednextError::InvalidInput(format!("Item {id} cannot depend on itself")).into(),
+            );
+        }
+        for i in [id, depends_on] {
+            if self.get(i)?.is_none() {
+                return Err(RednextError::ItemNotFound(i).into());
+            }
+        }
 
-#[cfg(test)]
-mod tests {
-    use chrono::NaiveDate;
-    use rusqlite::Connection;
+        let cycle: bool = self
+            .connection
+            .query_one(
+                "WITH RECURSIVE prerequisites(id) AS (
+                   SELECT ?1
+                   UNION
+                   SELECT deps.depends_on FROM deps JOIN prerequisites ON deps.item_id = prerequisites.id
+                 )
+                 SELECT EXISTS (SELECT 1 FROM prerequisites WHERE id = ?2)",
+                params![depends_on, id],
+                |row| row.get(0),
+            )
+            .context("Query error")?;
+        if cycle {
+            return Err(RednextError::Conflict(format!(
+                "Item {depends_on} already depends on item {id}, this would create a cycle"
+            ))
+            .into());
+        }
 
-    use crate::{
-        db::{DBFile, DbField, DbFieldDesc, DbFieldType, DbSchema, DbValue},
-        sqlite::SqliteFile,
-    };
+        self.connection
+            .execute(
+                "INSERT OR IGNORE INTO deps (item_id, depends_on) VALUES (?1, ?2)",
+                params![id, depends_on],
+            )
+            .context("Cannot add dependency")?;
+        Ok(())
+    }
 
-    fn create_file() -> SqliteFile {
-        let conn = Connection::open_in_memory().unwrap();
-        conn.execute(
-            "CREATE TABLE items(
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            txt TEXT,
-            due TIMESTAMP,
-            n NUMBER,
+    fn remove_dependency(&self, id: u32, depends_on: u32) -> Result<()> {
+        let count = self
+            .connection
+            .execute(
+                "DELETE FROM deps WHERE item_id=?1 AND depends_on=?2",
+                params![id, depends_on],
+            )
+            .context("Cannot remove dependency")?;
+        if count == 1 {
+            Ok(())
+        } else {
+            Err(
+                RednextError::Conflict(format!("Item {id} doesn't depend on item {depends_on}"))
+                    .into(),
+            )
+        }
+    }
+
+    fn pin(&self, id: u32) -> Result<()> {
+        let count = self
+            .connection
+            .execute(
+                "UPDATE items
+                 SET _rn_pinned=(SELECT ifnull(max(_rn_pinned), 0) + 1 FROM items)
+                 WHERE id=?1 AND _rn_pinned IS NULL AND done_at IS NULL",
+                params![id],
+            )
+            .context("Cannot pin item")?;
+        if count == 1 {
+            return Ok(());
+        }
+        match self.get(id)? {
+            Some(item) if item.completed_at.is_some() => {
+                Err(RednextError::Conflict(format!("Item {id} is already done")).into())
+            }
+            Some(_) => Ok(()),
+            None => Err(RednextError::ItemNotFound(id).into()),
+        }
+    }
+
+    fn unpin(&self, id: u32) -> Result<()> {
+        let count = self
+            .connection
+            .execute(
+                "UPDATE items SET _rn_pinned=NULL WHERE id=?1 AND _rn_pinned IS NOT NULL",
+                params![id],
+            )
+            .context("Cannot unpin item")?;
+        if count == 1 {
+            Ok(())
+        } else {
+            Err(RednextError::Conflict(format!("Item with id {id} is not pinned")).into())
+        }
+    }
+
+    fn move_to(&self, id: u32, position: u32) -> Result<()> {
+        // Taking the write lock at once, since the positions are read and then rewritten
+        let tx = Transaction::new_unchecked(&self.connection, TransactionBehavior::Immediate)?;
+        let mut ids = tx
+            .prepare("SELECT id FROM items ORDER BY _rn_position, id")?
+            .query_map([], |row| row.get::<_, u32>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("Query error")?;
+        let idx = ids
+            .iter()
+            .position(|&i| i == id)
+            .ok_or(RednextError::ItemNotFound(id))?;
+        ids.remove(idx);
+        let new_idx = (position.max(1) as usize - 1).min(ids.len());
+        ids.insert(new_idx, id);
+        {
+            let mut stmt = tx.prepare("UPDATE items SET _rn_position=?1 WHERE id=?2")?;
+            for (pos, id) in (1u32..).zip(ids) {
+                stmt.execute(params![pos, id])
+                    .context("Cannot update item position")?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn set_effort_field(&mut self, field: Option<&str>) -> Result<()> {
+        if let Some(field) = field {
+            match self.schema.fields.iter().find(|f| f.name == field) {
+                Some(f) if f.field_type == DbFieldType::Number => {}
+                Some(_) => {
+                    return Err(RednextError::SchemaMismatch(format!(
+                        "Effort field {field} must be a number"
+                    ))
+                    .into());
+                }
+                None => {
+                    return Err(
+                        RednextError::SchemaMismatch(format!("Unknown field {field}")).into(),
+                    );
+                }
+            }
+        }
+        SqliteDB::write_effort_field(&self.connection, field).context("Cannot write schema")?;
+        self.schema.effort_field = field.map(|f| f.to_string());
+        Ok(())
+    }
+
+    fn record_draw(&self, id: u32, time: NaiveDateTime) -> Result<()> {
+        self.connection
+            .execute(
+                "INSERT INTO draws (item_id, drawn_at) VALUES (?1, ?2)",
+                params![id, time],
+            )
+            .context("Cannot record draw")?;
+        Ok(())
+    }
+
+    fn draws(&self, id: u32) -> Result<Vec<NaiveDateTime>> {
+        let mut stmt = self
+            .connection
+            .prepare("SELECT drawn_at FROM draws WHERE item_id = ?1 ORDER BY drawn_at, id")?;
+        stmt.query_map(params![id], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("Query error")
+    }
+
+    fn dependencies(&self, id: u32) -> Result<Vec<u32>> {
+        let mut stmt = self
+            .connection
+            .prepare("SELECT depends_on FROM deps WHERE item_id = ?1 ORDER BY depends_on")?;
+        stmt.query_map(params![id], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("Query error")
+    }
+
+    fn summary(&self) -> Result<FileSummary> {
+        SqliteDB::read_summary(&self.connection).context("Query error")
+    }
+}
+
+impl FromSql for DbFieldType {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()?
+            .parse()
+            .map_err(|e| FromSqlError::Other(Box::new(e)))
+    }
+}
+
+/// Parses filter time. A date without time is a range from the start of the day to the start of
+/// the next day, otherwise both ends are the same.
+fn time_range(s: &str) -> Option<(NaiveDateTime, NaiveDateTime)> {
+    ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(s, fmt).ok())
+        .map(|dt| (dt, dt))
+        .or_else(|| {
+            let date = NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()?;
+            let next = date.checked_add_days(Days::new(1))?;
+            Some((date.and_hms_opt(0, 0, 0)?, next.and_hms_opt(0, 0, 0)?))
+        })
+}
+
+/// Quotes SQL identifier, doubling the quotes inside it.
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Runs the operation again while the file is locked by other processes. The busy timeout
+/// covers most cases, but some locks fail at once, e.g. when the journal mode is being changed,
+/// or when a transaction started reading before another process wrote to the file.
+fn retry_busy<T>(mut f: impl FnMut() -> rusqlite::Result<T>) -> rusqlite::Result<T> {
+    let mut attempt = 1;
+    loop {
+        match f() {
+            Err(e)
+                if attempt < BUSY_RETRIES
+                    && matches!(
+                        e.sqlite_error_code(),
+                        Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked)
+                    ) =>
+            {
+                thread::sleep(RETRY_DELAY * attempt);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Checks that the name can be used for a file in the DB directory.
+fn validate_file_name(name: &str) -> Result<()> {
+    let error = |reason: &str| -> Result<()> {
+        Err(RednextError::InvalidInput(format!("Invalid file name {name:?}: {reason}")).into())
+    };
+    if name.is_empty() {
+        error("name is empty")
+    } else if name.contains(['/', '\\']) {
+        error("use a path with .db extension for files outside of the DB directory")
+    } else if name.starts_with('.') {
+        error("name cannot start with a dot")
+    } else if name
+        .chars()
+        .any(|c| c.is_control() || ":*?\"<>|".contains(c))
+    {
+        error("name contains special characters")
+    } else {
+        Ok(())
+    }
+}
+
+/// Quoted names of the text fields, which are included into the search index.
+fn text_columns(schema: &DbSchema) -> Vec<String> {
+    schema
+        .fields
+        .iter()
+        .filter(|f| f.field_type == DbFieldType::Text)
+        .map(|f| quote_ident(&f.name))
+        .collect()
+}
+
+/// Converts the search text into FTS5 query. All words must match, words ending with `*` are
+/// prefixes, and text in double quotes is a phrase. Returns `None` if there is nothing to search.
+fn fts_query(text: &str) -> Option<String> {
+    let mut terms = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        let (term, tail) = match rest.strip_prefix('"') {
+            Some(phrase) => phrase.split_once('"').unwrap_or((phrase, "")),
+            None => rest.split_once(char::is_whitespace).unwrap_or((rest, "")),
+        };
+        let (term, prefix) = match term.strip_suffix('*') {
+            Some(t) => (t, "*"),
+            None => (term, ""),
+        };
+        let term = term.trim();
+        if !term.is_empty() {
+            terms.push(format!("\"{}\"{prefix}", term.replace('"', "\"\"")));
+        }
+        rest = tail.trim_start();
+    }
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// Returns byte ranges of the text between match markers, as if there were no markers.
+fn match_ranges(highlighted: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut pos = 0;
+    let mut start = 0;
+    for c in highlighted.chars() {
+        match c {
+            MATCH_START => start = pos,
+            MATCH_END => ranges.push(start..pos),
+            _ => pos += c.len_utf8(),
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+        thread,
+        time::Duration,
+    };
+
+    use chrono::{NaiveDate, NaiveDateTime};
+    use rand::{SeedableRng, rngs::ChaCha8Rng};
+    use rusqlite::Connection;
+
+    use crate::{
+        db::{
+            Cooldown, DB, DBFile, DbField, DbFieldDesc, DbFieldType, DbSchema, DbValue,
+            DrawOptions, FileNotFound, Filter, FoundItem, ItemQuery, ItemStatus,
+        },
+        error::RednextError,
+        sqlite::{SqliteDB, SqliteFile, fts_query, match_ranges},
+    };
+
+    fn create_file() -> SqliteFile {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE items(
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            txt TEXT,
+            due TIMESTAMP,
+            n NUMBER,
             bool BOOLEAN,
             done_at TIMESTAMP,
             comment TEXT
@@ -386,46 +1527,58 @@ mod tests {
                 DbFieldDesc::new("bool", DbFieldType::Boolean),
                 DbFieldDesc::new("n", DbFieldType::Number),
             ],
+            effort_field: None,
         };
-        SqliteFile {
-            connection: conn,
-            schema,
-        }
+        SqliteDB::write_schema(&mut conn, &schema).unwrap();
+        SqliteDB::migrate(&mut conn).unwrap();
+        SqliteDB::ensure_search_index(&mut conn, &schema).unwrap();
+        SqliteFile::new(conn, schema).unwrap()
+    }
+
+    fn created() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 6, 1)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
     }
 
     #[test]
     fn test_insert() {
         let file = create_file();
         assert!(file.list_items().unwrap().is_empty());
-        file.insert(&[
-            DbField {
-                name: "txt".to_string(),
-                value: DbValue::Text("task 1".to_string()),
-            },
-            DbField {
-                name: "due".to_string(),
-                value: DbValue::DateTime(
-                    chrono::NaiveDate::from_ymd_opt(2024, 7, 1)
-                        .unwrap()
-                        .and_hms_opt(1, 20, 0)
-                        .unwrap(),
-                ),
-            },
-            DbField {
-                name: "bool".to_string(),
-                value: DbValue::Boolean(true),
-            },
-            DbField {
-                name: "n".to_string(),
-                value: DbValue::Number(42),
-            },
-        ])
+        file.insert(
+            &[
+                DbField {
+                    name: "txt".to_string(),
+                    value: DbValue::Text("task 1".to_string()),
+                },
+                DbField {
+                    name: "due".to_string(),
+                    value: DbValue::DateTime(
+                        chrono::NaiveDate::from_ymd_opt(2024, 7, 1)
+                            .unwrap()
+                            .and_hms_opt(1, 20, 0)
+                            .unwrap(),
+                    ),
+                },
+                DbField {
+                    name: "bool".to_string(),
+                    value: DbValue::Boolean(true),
+                },
+                DbField {
+                    name: "n".to_string(),
+                    value: DbValue::Number(42),
+                },
+            ],
+            created(),
+        )
         .unwrap();
 
         let items = file.list_items().unwrap();
         assert_eq!(items.len(), 1);
         let item = &items[0];
         assert_eq!(item.id, 1);
+        assert_eq!(item.created_at, Some(created()));
         assert_eq!(item.fields.len(), 4);
         assert_eq!(item.fields[0].name, "txt");
         assert_eq!(item.fields[0].value, DbValue::Text("task 1".to_string()));
@@ -444,4 +1597,815 @@ mod tests {
         assert_eq!(item.fields[3].name, "n");
         assert_eq!(item.fields[3].value, DbValue::Number(42));
     }
+
+    fn insert_text(file: &SqliteFile, txt: &str) {
+        file.insert(
+            &[
+                DbField {
+                    name: "txt".to_string(),
+                    value: DbValue::Text(txt.to_string()),
+                },
+                DbField {
+                    name: "due".to_string(),
+                    value: DbValue::DateTime(
+                        NaiveDate::from_ymd_opt(2024, 7, 1)
+                            .unwrap()
+                            .and_hms_opt(0, 0, 0)
+                            .unwrap(),
+                    ),
+                },
+                DbField {
+                    name: "bool".to_string(),
+                    value: DbValue::Boolean(false),
+                },
+                DbField {
+                    name: "n".to_string(),
+                    value: DbValue::Number(1),
+                },
+            ],
+            created(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_get_random_with_seed() {
+        let file = create_file();
+        assert!(
+            file.get_random(&mut ChaCha8Rng::seed_from_u64(1), &DrawOptions::default())
+                .unwrap()
+                .is_none()
+        );
+        for i in 1..=20 {
+            insert_text(&file, &format!("task {i}"));
+        }
+
+        let first = file
+            .get_random(&mut ChaCha8Rng::seed_from_u64(42), &DrawOptions::default())
+            .unwrap()
+            .unwrap();
+        let second = file
+            .get_random(&mut ChaCha8Rng::seed_from_u64(42), &DrawOptions::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.id, second.id);
+
+        file.done(
+            first.id,
+            NaiveDate::from_ymd_opt(2024, 7, 2)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+        )
+        .unwrap();
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        for _ in 0..20 {
+            let item = file
+                .get_random(&mut rng, &DrawOptions::default())
+                .unwrap()
+                .unwrap();
+            assert_ne!(item.id, first.id);
+        }
+    }
+
+    #[test]
+    fn test_get_random_sparse() {
+        let file = create_file();
+        for i in 1..=200 {
+            insert_text(&file, &format!("task {i}"));
+        }
+        let time = NaiveDate::from_ymd_opt(2024, 7, 2)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        // Random ids rarely hit the remaining items, so they are usually counted
+        for id in (2..200).filter(|id| *id != 100) {
+            file.done(id, time).unwrap();
+        }
+
+        let mut rng = ChaCha8Rng::seed_from_u64(5);
+        let mut drawn = (0..50)
+            .map(|_| {
+                file.get_random(&mut rng, &DrawOptions::default())
+                    .unwrap()
+                    .unwrap()
+                    .id
+            })
+            .collect::<Vec<_>>();
+        drawn.sort();
+        drawn.dedup();
+        assert_eq!(drawn, vec![1, 100, 200]);
+    }
+
+    #[test]
+    fn test_dependencies() {
+        let file = create_file();
+        for i in 1..=3 {
+            insert_text(&file, &format!("book {i}"));
+        }
+        file.add_dependency(2, 1).unwrap();
+        file.add_dependency(3, 2).unwrap();
+        assert!(file.add_dependency(1, 3).is_err());
+        assert!(file.add_dependency(1, 1).is_err());
+        assert!(file.add_dependency(1, 4).is_err());
+
+        let blocked = file
+            .list_items()
+            .unwrap()
+            .iter()
+            .map(|i| i.blocked)
+            .collect::<Vec<_>>();
+        assert_eq!(blocked, vec![false, true, true]);
+
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        for _ in 0..10 {
+            assert_eq!(
+                file.get_random(&mut rng, &DrawOptions::default())
+                    .unwrap()
+                    .unwrap()
+                    .id,
+                1
+            );
+        }
+
+        let time = NaiveDate::from_ymd_opt(2024, 7, 2)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        file.done(1, time).unwrap();
+        assert!(!file.get(2).unwrap().unwrap().blocked);
+        assert!(file.get(3).unwrap().unwrap().blocked);
+
+        assert_eq!(file.dependencies(3).unwrap(), vec![2]);
+        file.remove_dependency(3, 2).unwrap();
+        assert!(!file.get(3).unwrap().unwrap().blocked);
+        assert!(file.dependencies(3).unwrap().is_empty());
+        assert!(file.remove_dependency(3, 2).is_err());
+
+        file.add_dependency(3, 2).unwrap();
+        file.delete(2).unwrap();
+        assert!(file.dependencies(3).unwrap().is_empty());
+        assert!(file.dependencies(2).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_pinned_and_sequential() {
+        let file = create_file();
+        for i in 1..=4 {
+            insert_text(&file, &format!("task {i}"));
+        }
+        assert_eq!(file.get_next(None, None).unwrap().unwrap().id, 1);
+
+        file.move_to(3, 1).unwrap();
+        let order = file
+            .list_items()
+            .unwrap()
+            .iter()
+            .map(|i| i.id)
+            .collect::<Vec<_>>();
+        assert_eq!(order, vec![3, 1, 2, 4]);
+        assert_eq!(file.get_next(None, None).unwrap().unwrap().id, 3);
+        assert!(file.get_next(Some("unknown"), None).is_err());
+
+        file.pin(4).unwrap();
+        file.pin(2).unwrap();
+        assert_eq!(file.get_next(None, None).unwrap().unwrap().id, 4);
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        assert_eq!(
+            file.get_random(&mut rng, &DrawOptions::default())
+                .unwrap()
+                .unwrap()
+                .id,
+            4
+        );
+
+        let time = NaiveDate::from_ymd_opt(2024, 7, 2)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        file.done(4, time).unwrap();
+        let err = file.pin(4).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<RednextError>(),
+            Some(RednextError::Conflict(_))
+        ));
+        assert_eq!(
+            file.get_random(&mut rng, &DrawOptions::default())
+                .unwrap()
+                .unwrap()
+                .id,
+            2
+        );
+        file.unpin(2).unwrap();
+        assert!(file.unpin(2).is_err());
+        assert!(
+            file.query(&ItemQuery::status(ItemStatus::Pinned))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_balance_and_cooldown() {
+        let file = create_file();
+        for _ in 1..=10 {
+            insert_text(&file, "common");
+        }
+        insert_text(&file, "rare");
+        let time = NaiveDate::from_ymd_opt(2024, 7, 2)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+
+        let balanced = DrawOptions {
+            balance_by: Some("txt".to_string()),
+            ..Default::default()
+        };
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        let rare_count = (0..40)
+            .filter(|_| file.get_random(&mut rng, &balanced).unwrap().unwrap().id == 11)
+            .count();
+        // Each value is chosen with the same probability
+        assert!(rare_count > 10);
+
+        let cooldown = DrawOptions {
+            cooldown: vec![Cooldown {
+                field: "txt".to_string(),
+                draws: 1,
+            }],
+            ..Default::default()
+        };
+        file.record_draw(11, time).unwrap();
+        for _ in 0..20 {
+            let item = file.get_random(&mut rng, &cooldown).unwrap().unwrap();
+            assert_ne!(item.id, 11);
+        }
+        // When all the values are recent, the cooldown is ignored
+        file.record_draw(1, time).unwrap();
+        let all_recent = DrawOptions::default().with_cooldown(vec![Cooldown {
+            field: "txt".to_string(),
+            draws: 2,
+        }]);
+        assert!(file.get_random(&mut rng, &all_recent).unwrap().is_some());
+
+        let unknown = DrawOptions {
+            balance_by: Some("unknown".to_string()),
+            ..Default::default()
+        };
+        assert!(file.get_random(&mut rng, &unknown).is_err());
+    }
+
+    #[test]
+    fn test_max_effort() {
+        let mut file = create_file();
+        for i in 1..=3 {
+            insert_text(&file, &format!("task {i}"));
+        }
+        file.connection
+            .execute("UPDATE items SET n=120 WHERE id<>2", [])
+            .unwrap();
+        let options = DrawOptions {
+            max_effort: Some(30),
+            ..Default::default()
+        };
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        assert!(file.get_random(&mut rng, &options).is_err());
+
+        assert!(file.set_effort_field(Some("txt")).is_err());
+        file.set_effort_field(Some("n")).unwrap();
+        assert_eq!(file.schema().effort_field.as_deref(), Some("n"));
+        for _ in 0..10 {
+            assert_eq!(file.get_random(&mut rng, &options).unwrap().unwrap().id, 2);
+        }
+        assert_eq!(file.get_next(None, Some(30)).unwrap().unwrap().id, 2);
+        assert_eq!(file.get_next(None, None).unwrap().unwrap().id, 1);
+
+        file.done(
+            2,
+            NaiveDate::from_ymd_opt(2024, 7, 2)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(file.get_random(&mut rng, &options).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_summary() {
+        let file = create_file();
+        let summary = file.summary().unwrap();
+        assert_eq!(summary.items, 0);
+        assert_eq!(summary.last_activity, None);
+
+        insert_text(&file, "task 1");
+        insert_text(&file, "task 2");
+        let done = NaiveDate::from_ymd_opt(2024, 7, 2)
+            .unwrap()
+            .and_hms_opt(10, 0, 0)
+            .unwrap();
+        file.done(2, done).unwrap();
+        let summary = file.summary().unwrap();
+        assert_eq!(summary.items, 2);
+        assert_eq!(summary.done, 1);
+        assert_eq!(summary.last_activity, Some(done));
+
+        let drawn = done + chrono::Days::new(1);
+        file.record_draw(1, drawn).unwrap();
+        assert_eq!(file.summary().unwrap().last_activity, Some(drawn));
+        assert_eq!(file.draws(1).unwrap(), vec![drawn]);
+        assert!(file.draws(2).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_internal_columns_migration() {
+        // Field which used to clash with the internal column, in a file of the first version
+        let mut conn = Connection::open_in_memory().unwrap();
+        let schema = DbSchema {
+            fields: vec![
+                DbFieldDesc::new("title", DbFieldType::Text),
+                DbFieldDesc::new("position", DbFieldType::Number),
+            ],
+            effort_field: None,
+        };
+        SqliteDB::write_schema(&mut conn, &schema).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE items (id INTEGER PRIMARY KEY AUTOINCREMENT, title TEXT, position NUMBER,
+               done_at TIMESTAMP);
+             INSERT INTO items (title, position) VALUES ('Dune', 3);
+             PRAGMA user_version = 0;",
+        )
+        .unwrap();
+        SqliteDB::migrate(&mut conn).unwrap();
+        let file = SqliteFile::new(conn, schema).unwrap();
+        let item = file.get(1).unwrap().unwrap();
+        assert_eq!(item.fields[1].value, DbValue::Number(3));
+        assert_eq!(file.get_next(None, None).unwrap().unwrap().id, 1);
+    }
+
+    /// Creates in-memory file the same way as `SqliteDB::create` does.
+    fn create_with_schema(fields: Vec<DbFieldDesc>) -> SqliteFile {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let schema = DbSchema {
+            fields,
+            effort_field: None,
+        };
+        SqliteDB::write_schema(&mut conn, &schema).unwrap();
+        SqliteDB::create_items_table(&mut conn, &schema).unwrap();
+        SqliteDB::migrate(&mut conn).unwrap();
+        SqliteDB::ensure_search_index(&mut conn, &schema).unwrap();
+        SqliteFile::new(conn, schema).unwrap()
+    }
+
+    #[test]
+    fn test_find() {
+        let file = create_file();
+        insert_text(&file, "Dune Messiah and other stories");
+        insert_text(&file, "The Hobbit");
+        insert_text(&file, "Dune");
+        insert_text(&file, "École de Paris");
+
+        let ids = |items: Vec<FoundItem>| items.iter().map(|i| i.item.id).collect::<Vec<_>>();
+        assert_eq!(ids(file.find("hobbit", &[], &[]).unwrap()), vec![2]);
+        // Shorter text with the same match is more relevant
+        assert_eq!(ids(file.find("dune", &[], &[]).unwrap()), vec![3, 1]);
+        assert_eq!(ids(file.find("mess*", &[], &[]).unwrap()), vec![1]);
+        assert!(file.find("mess", &[], &[]).unwrap().is_empty());
+        assert_eq!(
+            ids(file.find("\"other stories\"", &[], &[]).unwrap()),
+            vec![1]
+        );
+        assert!(file.find("\"stories other\"", &[], &[]).unwrap().is_empty());
+        assert_eq!(ids(file.find("ÉCOLE", &[], &[]).unwrap()), vec![4]);
+        assert_eq!(ids(file.find("ecole", &[], &[]).unwrap()), vec![4]);
+        assert_eq!(
+            ids(file.find("dune", &["txt".to_string()], &[]).unwrap()),
+            vec![3, 1]
+        );
+        let err = file
+            .find("dune", &["author".to_string()], &[])
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.downcast_ref::<RednextError>(),
+            Some(RednextError::SchemaMismatch(_))
+        ));
+        // Only text fields are searched
+        let err = file.find("1", &["n".to_string()], &[]).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<RednextError>(),
+            Some(RednextError::SchemaMismatch(_))
+        ));
+        assert!(file.find(" \"\" ", &[], &[]).unwrap().is_empty());
+
+        let found = file.find("hob*", &[], &[]).unwrap();
+        assert_eq!(found[0].matches, vec![vec![4..10], vec![], vec![], vec![]]);
+
+        file.delete(2).unwrap();
+        assert!(file.find("hobbit", &[], &[]).unwrap().is_empty());
+        file.connection
+            .execute("UPDATE items SET txt = 'Arrakis' WHERE id = 3", [])
+            .unwrap();
+        assert_eq!(ids(file.find("dune", &[], &[]).unwrap()), vec![1]);
+        assert_eq!(ids(file.find("arrakis", &[], &[]).unwrap()), vec![3]);
+    }
+
+    #[test]
+    fn test_find_field_names() {
+        let file = create_with_schema(vec![
+            DbFieldDesc::new("book \"title\"", DbFieldType::Text),
+            DbFieldDesc::new("pages", DbFieldType::Number),
+        ]);
+        file.insert(
+            &[
+                DbField {
+                    name: "book \"title\"".to_string(),
+                    value: DbValue::Text("The Hobbit".to_string()),
+                },
+                DbField {
+                    name: "pages".to_string(),
+                    value: DbValue::Number(310),
+                },
+            ],
+            created(),
+        )
+        .unwrap();
+        assert_eq!(file.find("hobbit", &[], &[]).unwrap().len(), 1);
+        assert_eq!(
+            file.find("hobbit", &["book \"title\"".to_string()], &[])
+                .unwrap()
+                .len(),
+            1
+        );
+        let query = ItemQuery {
+            filters: vec!["book \"title\"~Hob".parse().unwrap()],
+            sort: vec!["book \"title\":desc".parse().unwrap()],
+            ..ItemQuery::default()
+        };
+        assert_eq!(file.query(&query).unwrap().len(), 1);
+        assert!(
+            file.get_next(Some("book \"title\""), None)
+                .unwrap()
+                .is_some()
+        );
+
+        let file = create_with_schema(vec![DbFieldDesc::new("pages", DbFieldType::Number)]);
+        assert!(file.find("hobbit", &[], &[]).unwrap().is_empty());
+
+        // Same names as the search results had before they were prefixed
+        let file = create_with_schema(vec![
+            DbFieldDesc::new("hl0", DbFieldType::Text),
+            DbFieldDesc::new("hit_id", DbFieldType::Number),
+        ]);
+        file.insert(
+            &[
+                DbField {
+                    name: "hl0".to_string(),
+                    value: DbValue::Text("The Hobbit".to_string()),
+                },
+                DbField {
+                    name: "hit_id".to_string(),
+                    value: DbValue::Number(7),
+                },
+            ],
+            created(),
+        )
+        .unwrap();
+        let found = file.find("hobbit", &[], &[]).unwrap();
+        assert_eq!(found[0].item.id, 1);
+        assert_eq!(found[0].matches, vec![vec![4..10], vec![]]);
+    }
+
+    #[test]
+    fn test_filters() {
+        let file = create_file();
+        let add = |txt: &str, day: u32, hour: u32, b: bool, n: i32| {
+            let due = NaiveDate::from_ymd_opt(2024, 7, day)
+                .unwrap()
+                .and_hms_opt(hour, 0, 0)
+                .unwrap();
+            let values = [
+                DbValue::Text(txt.to_string()),
+                DbValue::DateTime(due),
+                DbValue::Boolean(b),
+                DbValue::Number(n),
+            ];
+            let fields = file
+                .schema
+                .fields
+                .iter()
+                .zip(values)
+                .map(|(f, value)| DbField {
+                    name: f.name.clone(),
+                    value,
+                })
+                .collect::<Vec<_>>();
+            file.insert(&fields, created()).unwrap();
+        };
+        add("The Hobbit", 1, 10, true, 310);
+        add("Dune", 1, 23, false, 412);
+        add("The Silmarillion", 2, 0, false, 365);
+
+        let ids = |filters: &[&str]| {
+            let filters = filters.iter().map(|f| f.parse().unwrap()).collect();
+            file.query(&ItemQuery {
+                filters,
+                ..Default::default()
+            })
+            .unwrap()
+            .iter()
+            .map(|i| i.id)
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(&["txt=Dune"]), vec![2]);
+        assert_eq!(ids(&["txt!=Dune"]), vec![1, 3]);
+        assert_eq!(ids(&["txt~^The"]), vec![1, 3]);
+        assert_eq!(ids(&["txt~(?i)hobbit"]), vec![1]);
+        assert_eq!(ids(&["n>=365"]), vec![2, 3]);
+        assert_eq!(ids(&["n>=365", "n<400"]), vec![3]);
+        assert_eq!(ids(&["bool=true"]), vec![1]);
+        assert_eq!(ids(&["bool != true"]), vec![2, 3]);
+        // Date without time is the whole day
+        assert_eq!(ids(&["due=2024-07-01"]), vec![1, 2]);
+        assert_eq!(ids(&["due<=2024-07-01"]), vec![1, 2]);
+        assert_eq!(ids(&["due>2024-07-01"]), vec![3]);
+        assert_eq!(ids(&["due<2024-07-01 23:00:00"]), vec![1]);
+        assert_eq!(ids(&["due>=2024-07-01 23:00:00"]), vec![2, 3]);
+
+        let check = |filter: &str| {
+            file.query(&ItemQuery {
+                filters: vec![filter.parse().unwrap()],
+                ..Default::default()
+            })
+        };
+        assert!(check("author=Tolkien").is_err());
+        assert!(check("n=many").is_err());
+        assert!(check("n~1").is_err());
+        assert!(check("txt<B").is_err());
+        assert!(check("bool=yes").is_err());
+        assert!(check("bool>false").is_err());
+        assert!(check("due=tomorrow").is_err());
+        assert!(check("txt~(").is_err());
+
+        let filters = vec!["bool=true".parse::<Filter>().unwrap()];
+        let found = file.find("the", &[], &filters).unwrap();
+        assert_eq!(found.iter().map(|f| f.item.id).collect::<Vec<_>>(), vec![1]);
+        assert_eq!(file.find("", &[], &filters).unwrap().len(), 1);
+        assert!(file.find("", &[], &[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sort_and_limit() {
+        let file = create_file();
+        for txt in ["b", "c", "a", "c"] {
+            insert_text(&file, txt);
+        }
+        let ids = |query: &ItemQuery| {
+            file.query(query)
+                .unwrap()
+                .iter()
+                .map(|i| i.id)
+                .collect::<Vec<_>>()
+        };
+        let sort = |keys: &[&str]| ItemQuery {
+            sort: keys.iter().map(|k| k.parse().unwrap()).collect(),
+            ..Default::default()
+        };
+        assert_eq!(ids(&sort(&["txt"])), vec![3, 1, 2, 4]);
+        assert_eq!(ids(&sort(&["txt:desc", "id:desc"])), vec![4, 2, 1, 3]);
+        assert!(file.query(&sort(&["author"])).is_err());
+
+        let page = ItemQuery {
+            limit: Some(2),
+            offset: 1,
+            ..sort(&["txt"])
+        };
+        assert_eq!(ids(&page), vec![1, 2]);
+        assert_eq!(
+            ids(&ItemQuery {
+                offset: 3,
+                ..Default::default()
+            }),
+            vec![4]
+        );
+        assert_eq!(file.count(&page).unwrap(), 4);
+        let filtered = ItemQuery {
+            filters: vec!["txt=c".parse().unwrap()],
+            ..Default::default()
+        };
+        assert_eq!(file.count(&filtered).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_for_each_item() {
+        let file = create_file();
+        for name in ["first", "second", "third"] {
+            insert_text(&file, name);
+        }
+        let mut ids = Vec::new();
+        let result = file.for_each_item(&ItemQuery::default(), &mut |item| {
+            ids.push(item.id);
+            if item.id == 2 {
+                Err(anyhow::anyhow!("stop"))
+            } else {
+                Ok(())
+            }
+        });
+        assert_eq!(result.unwrap_err().to_string(), "stop");
+        assert_eq!(ids, vec![1, 2]);
+
+        let mut found = Vec::new();
+        file.for_each_found("second", &[], &[], &mut |f| {
+            found.push(f.item.id);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(found, vec![2]);
+    }
+
+    #[test]
+    fn test_file_path() {
+        let db = SqliteDB::new(Path::new("/home/user/lists"));
+        assert_eq!(
+            db.file_path("books").unwrap(),
+            PathBuf::from("/home/user/lists/books.db")
+        );
+        assert_eq!(
+            db.file_path("books.v2").unwrap(),
+            PathBuf::from("/home/user/lists/books.v2.db")
+        );
+        assert_eq!(db.file_path("books.db").unwrap(), PathBuf::from("books.db"));
+        assert_eq!(
+            db.file_path("/srv/shared/todo.db").unwrap(),
+            PathBuf::from("/srv/shared/todo.db")
+        );
+        for name in [
+            "",
+            "project/todo",
+            "../books",
+            "..",
+            ".hidden",
+            "a\\b",
+            "a:b",
+            "a\nb",
+        ] {
+            assert!(db.file_path(name).is_err(), "{name:?} is accepted");
+        }
+    }
+
+    #[test]
+    fn test_open_missing() {
+        let dir = std::env::temp_dir().join(format!("rednext-test-{}", std::process::id()));
+        let db = SqliteDB::new(&dir);
+        let schema = DbSchema {
+            fields: vec![DbFieldDesc::new("name", DbFieldType::Text)],
+            effort_field: None,
+        };
+        db.create("books", schema).unwrap();
+
+        let err = db.open("boks").err().unwrap();
+        let Some(RednextError::FileNotFound(not_found)) = err.downcast_ref() else {
+            panic!("Unexpected error {err}");
+        };
+        assert_eq!(
+            not_found,
+            &FileNotFound {
+                name: "boks".to_string(),
+                suggestions: vec!["books".to_string()],
+            }
+        );
+        assert!(!dir.join("boks.db").exists());
+        assert!(db.delete("boks").is_err());
+        assert!(db.open("books").is_ok());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_summary_read_only() {
+        let dir = std::env::temp_dir().join(format!("rednext-test-ro-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        // File of the first version, which would be upgraded when opened
+        let conn = Connection::open(dir.join("old.db")).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE schema (name TEXT PRIMARY KEY, datatype TEXT NOT NULL, idx INTEGER);
+             INSERT INTO schema VALUES ('title', 'Text', 0);
+             CREATE TABLE items (id INTEGER PRIMARY KEY AUTOINCREMENT, title TEXT,
+               done_at TIMESTAMP);
+             INSERT INTO items (title, done_at)
+             VALUES ('Dune', '2026-03-01 10:00:00'), ('Emma', NULL);",
+        )
+        .unwrap();
+        fs::write(dir.join("broken.db"), "not a database").unwrap();
+
+        let db = SqliteDB::new(&dir);
+        let (schema, summary) = db.summary("old").unwrap();
+        assert_eq!(schema.fields[0].name, "title");
+        assert_eq!((summary.items, summary.done), (2, 1));
+        assert_eq!(
+            summary.last_activity,
+            NaiveDate::from_ymd_opt(2026, 3, 1)
+                .unwrap()
+                .and_hms_opt(10, 0, 0)
+        );
+        let version: u32 = conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, 0);
+        assert!(db.summary("broken").is_err());
+
+        drop(conn);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_concurrent_access() {
+        let dir = std::env::temp_dir().join(format!("rednext-test-wal-{}", std::process::id()));
+        let db = SqliteDB::new(&dir).with_busy_timeout(Duration::from_secs(10));
+        let schema = DbSchema {
+            fields: vec![DbFieldDesc::new("name", DbFieldType::Text)],
+            effort_field: None,
+        };
+        db.create("books", schema).unwrap();
+        let conn = Connection::open(dir.join("books.db")).unwrap();
+        let mode: String = conn
+            .pragma_query_value(None, "journal_mode", |row| row.get(0))
+            .unwrap();
+        assert_eq!(mode, "wal");
+
+        // Each thread has its own connection, like separate processes
+        thread::scope(|s| {
+            for t in 0..4 {
+                let db = &db;
+                s.spawn(move || {
+                    let file = db.open("books").unwrap();
+                    for i in 0..20 {
+                        let fields = [DbField {
+                            name: "name".to_string(),
+                            value: DbValue::Text(format!("book {t}-{i}")),
+                        }];
+                        file.insert(&fields, created()).unwrap();
+                    }
+                });
+            }
+        });
+
+        let first = db.open("books").unwrap();
+        let second = db.open("books").unwrap();
+        assert_eq!(first.list_undone().unwrap().len(), 80);
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let drawn = first
+            .get_random(&mut rng, &DrawOptions::default())
+            .unwrap()
+            .unwrap();
+        first.complete(drawn.id, created()).unwrap();
+        let err = second.complete(drawn.id, created()).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(RednextError::Conflict(_))
+        ));
+        assert!(matches!(
+            second.complete(100, created()).unwrap_err().downcast_ref(),
+            Some(RednextError::ItemNotFound(100))
+        ));
+
+        first
+            .read_snapshot(&mut || {
+                let all = ItemQuery::default();
+                let count = first.count(&all)?;
+                let fields = [DbField {
+                    name: "name".to_string(),
+                    value: DbValue::Text("late book".to_string()),
+                }];
+                second.insert(&fields, created())?;
+                assert_eq!(first.count(&all)?, count);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(first.count(&ItemQuery::default()).unwrap(), 81);
+
+        drop((first, second, conn));
+        db.delete("books").unwrap();
+        assert!(!dir.join("books.db-wal").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_fts_query() {
+        assert_eq!(fts_query("lord  ring*").unwrap(), "\"lord\" \"ring\"*");
+        assert_eq!(
+            fts_query("\"the hobbit\" tolkien").unwrap(),
+            "\"the hobbit\" \"tolkien\""
+        );
+        assert_eq!(
+            fts_query("\"unclosed phrase").unwrap(),
+            "\"unclosed phrase\""
+        );
+        assert_eq!(fts_query("a\"b").unwrap(), "\"a\"\"b\"");
+        assert_eq!(fts_query(" * \"\" "), None);
+        assert_eq!(
+            match_ranges("a \u{2}bc\u{3} é\u{2}d\u{3}"),
+            vec![2..4, 7..8]
+        );
+    }
 }
@@ -0,0 +1,255 @@
+use std::collections::BTreeSet;
+
+use chrono::{Datelike, Days, Months, NaiveDate, TimeDelta, Weekday};
+
+use rednext::db::DbItem;
+
+/// Number of days used to calculate current pace
+const PACE_WINDOW: u64 = 28;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Period {
+    Day,
+    Week,
+    Month,
+}
+
+impl Period {
+    /// Returns the first day of the period containing the date. Weeks start on Monday.
+    pub fn start(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Day => date,
+            Period::Week => date.week(Weekday::Mon).first_day(),
+            Period::Month => date.with_day(1).unwrap(),
+        }
+    }
+
+    /// Returns the first day of the previous period.
+    pub fn previous(self, date: NaiveDate) -> NaiveDate {
+        let start = self.start(date);
+        match self {
+            Period::Day => start - Days::new(1),
+            Period::Week => start - Days::new(7),
+            Period::Month => start - Months::new(1),
+        }
+    }
+
+    /// Number of periods between the dates, both periods are included.
+    pub fn count(self, from: NaiveDate, to: NaiveDate) -> usize {
+        let (from, to) = (self.start(from), self.start(to));
+        let n = match self {
+            Period::Day => (to - from).num_days(),
+            Period::Week => (to - from).num_days() / 7,
+            Period::Month => {
+                (to.year() * 12 + to.month0() as i32 - from.year() * 12 - from.month0() as i32)
+                    as i64
+            }
+        };
+        n.max(0) as usize + 1
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct PeriodStats {
+    /// Completions during the current period
+    pub current: usize,
+    /// Completions during the previous period
+    pub previous: usize,
+    /// Average completions per period since the first completion
+    pub average: f64,
+}
+
+#[derive(Debug)]
+pub struct Stats {
+    pub total: usize,
+    pub done: usize,
+    pub days: PeriodStats,
+    pub weeks: PeriodStats,
+    pub months: PeriodStats,
+    /// Number of consecutive days with completions, up to today or yesterday
+    pub current_streak: usize,
+    pub longest_streak: usize,
+    /// Average time between creation and completion
+    pub average_completion: Option<TimeDelta>,
+    /// Date when all items will be done at the pace of the last weeks
+    pub projected_finish: Option<NaiveDate>,
+}
+
+impl Stats {
+    pub fn calculate(items: &[DbItem], today: NaiveDate) -> Self {
+        let completions = items
+            .iter()
+            .filter_map(|i| i.completed_at.map(|dt| dt.date()))
+            .collect::<Vec<_>>();
+        let days = completions.iter().copied().collect::<BTreeSet<_>>();
+
+        let durations = items
+            .iter()
+            .filter_map(|i| Some(i.completed_at? - i.created_at?))
+            .filter(|d| *d >= TimeDelta::zero())
+            .collect::<Vec<_>>();
+        let average_completion = if durations.is_empty() {
+            None
+        } else {
+            Some(durations.iter().sum::<TimeDelta>() / durations.len() as i32)
+        };
+
+        let remaining = items.len() - completions.len();
+
+        Stats {
+            total: items.len(),
+            done: completions.len(),
+            days: period_stats(&completions, Period::Day, today),
+            weeks: period_stats(&completions, Period::Week, today),
+            months: period_stats(&completions, Period::Month, today),
+            current_streak: current_streak(&days, today),
+            longest_streak: longest_streak(&days),
+            average_completion,
+            projected_finish: projected_finish(&completions, remaining, today),
+        }
+    }
+}
+
+fn period_stats(completions: &[NaiveDate], period: Period, today: NaiveDate) -> PeriodStats {
+    let current = period.start(today);
+    let previous = period.previous(today);
+    let count_in = |start: NaiveDate| {
+        completions
+            .iter()
+            .filter(|d| period.start(**d) == start)
+            .count()
+    };
+    let average = completions.iter().min().map_or(0.0, |first| {
+        completions.len() as f64 / period.count(*first, today) as f64
+    });
+    PeriodStats {
+        current: count_in(current),
+        previous: count_in(previous),
+        average,
+    }
+}
+
+fn current_streak(days: &BTreeSet<NaiveDate>, today: NaiveDate) -> usize {
+    // Today's streak is not broken until the day is over
+    let mut day = if days.contains(&today) {
+        today
+    } else {
+        today - Days::new(1)
+    };
+    let mut streak = 0;
+    while days.contains(&day) {
+        streak += 1;
+        day = day - Days::new(1);
+    }
+    streak
+}
+
+fn longest_streak(days: &BTreeSet<NaiveDate>) -> usize {
+    let mut longest = 0;
+    let mut streak = 0;
+    let mut prev: Option<NaiveDate> = None;
+    for day in days {
+        streak = match prev {
+            Some(p) if p + Days::new(1) == *day => streak + 1,
+            _ => 1,
+        };
+        longest = longest.max(streak);
+        prev = Some(*day);
+    }
+    longest
+}
+
+fn projected_finish(
+    completions: &[NaiveDate],
+    remaining: usize,
+    today: NaiveDate,
+) -> Option<NaiveDate> {
+    let first = completions.iter().min()?;
+    let window_start = today - Days::new(PACE_WINDOW - 1);
+    let recent = completions.iter().filter(|d| **d >= window_start).count();
+    let window_days = Period::Day.count(*first.max(&window_start), today);
+    let pace = recent as f64 / window_days as f64;
+    if pace > 0.0 {
+        let days = (remaining as f64 / pace).ceil() as u64;
+        Some(today + Days::new(days))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveDateTime, TimeDelta};
+
+    use rednext::db::DbItem;
+
+    use crate::stats::{Period, PeriodStats, Stats};
+
+    fn date(m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, m, d).unwrap()
+    }
+
+    fn time(m: u32, d: u32, h: u32) -> NaiveDateTime {
+        date(m, d).and_hms_opt(h, 0, 0).unwrap()
+    }
+
+    fn item(id: u32, created: NaiveDateTime, done: Option<NaiveDateTime>) -> DbItem {
+        DbItem::new(id, vec![])
+            .with_created_at(Some(created))
+            .with_completed_at(done)
+    }
+
+    #[test]
+    fn test_period() {
+        // 2026-03-11 is Wednesday
+        assert_eq!(Period::Week.start(date(3, 11)), date(3, 9));
+        assert_eq!(Period::Week.previous(date(3, 11)), date(3, 2));
+        assert_eq!(Period::Month.previous(date(3, 11)), date(2, 1));
+        assert_eq!(Period::Day.count(date(3, 1), date(3, 11)), 11);
+        assert_eq!(Period::Week.count(date(3, 8), date(3, 9)), 2);
+        assert_eq!(Period::Month.count(date(1, 31), date(3, 1)), 3);
+    }
+
+    #[test]
+    fn test_stats() {
+        let items = vec![
+            item(1, time(3, 1, 10), Some(time(3, 2, 10))),
+            item(2, time(3, 1, 10), Some(time(3, 3, 10))),
+            item(3, time(3, 1, 10), Some(time(3, 9, 22))),
+            item(4, time(3, 1, 10), Some(time(3, 10, 10))),
+            item(5, time(3, 1, 10), None),
+            item(6, time(3, 1, 10), None),
+        ];
+        let stats = Stats::calculate(&items, date(3, 11));
+        assert_eq!(stats.total, 6);
+        assert_eq!(stats.done, 4);
+        assert_eq!(
+            stats.days,
+            PeriodStats {
+                current: 0,
+                previous: 1,
+                average: 0.4
+            }
+        );
+        assert_eq!(stats.weeks.current, 2);
+        assert_eq!(stats.weeks.previous, 2);
+        assert_eq!(stats.months.current, 4);
+        assert_eq!(stats.current_streak, 2);
+        assert_eq!(stats.longest_streak, 2);
+        assert_eq!(stats.average_completion, Some(TimeDelta::hours(123)));
+        // 4 items in 10 days, 2 items remaining
+        assert_eq!(stats.projected_finish, Some(date(3, 16)));
+    }
+
+    #[test]
+    fn test_stats_empty() {
+        let items = vec![item(1, time(3, 1, 10), None)];
+        let stats = Stats::calculate(&items, date(3, 11));
+        assert_eq!(stats.done, 0);
+        assert_eq!(stats.days.average, 0.0);
+        assert_eq!(stats.current_streak, 0);
+        assert_eq!(stats.longest_streak, 0);
+        assert_eq!(stats.average_completion, None);
+        assert_eq!(stats.projected_finish, None);
+    }
+}